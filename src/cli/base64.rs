@@ -1,10 +1,10 @@
-use std::{fmt, str::FromStr};
+use std::{fmt, io::Write, str::FromStr};
 
 use anyhow::Result;
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 
-use crate::{process_decode, process_encode, CmdExecutor};
+use crate::{process_decode, process_encode, write_data, CmdExecutor};
 
 use super::verify_file;
 
@@ -32,6 +32,10 @@ pub struct Base64DecodeOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
 
+    // 解码结果原样写出, 不追加换行
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+
     #[arg(long, value_parser = verify_base64_format, default_value = "standard")]
     pub format: Base64Format,
 }
@@ -84,7 +88,9 @@ impl CmdExecutor for Base64EncodeOpts {
 impl CmdExecutor for Base64DecodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
         let decode = process_decode(&self.input, self.format)?;
-        println!("{}", decode);
+        let mut writer = write_data(&self.output)?;
+        writer.write_all(&decode)?;
+        writer.flush()?;
         Ok(())
     }
 }
@@ -1,14 +1,96 @@
 use anyhow::Result;
-use clap::Parser;
-use std::{fmt, str::FromStr};
+use clap::{ArgAction, Parser};
+use encoding_rs::Encoding;
+use enum_dispatch::enum_dispatch;
+use std::{
+    fmt,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    str::FromStr,
+};
 
-use crate::{process_csv, CmdExecutor};
+use crate::{
+    process_csv, process_csv_cat, process_csv_dedupe, process_csv_group, process_csv_join,
+    process_csv_query, process_csv_reverse, process_csv_sort, process_csv_split, process_csv_stats,
+    process_csv_validate, write_data, Aggregate, CmdExecutor, CsvReaderConfig, CsvTransform,
+    EncodeWriter, Inference, RowFilter, SortKey, SplitBy, TableSource, ValidationSpec,
+};
 
-use super::verify_file;
+use super::{verify_file, verify_path};
 
+// 不带子命令时保持 `rcli csv -i ...` 的转换行为
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct CsvOpts {
-    #[arg(short, long, value_parser = verify_file)]
+    #[command(subcommand)]
+    pub cmd: Option<CsvSubCommand>,
+
+    #[command(flatten)]
+    pub convert: CsvConvertOpts,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum CsvSubCommand {
+    #[command(name = "reverse", about = "Convert json/ndjson/yaml back to csv")]
+    Reverse(CsvReverseOpts),
+
+    #[command(name = "validate", about = "Validate csv rows against a schema")]
+    Validate(CsvValidateOpts),
+
+    #[command(name = "stats", about = "Print per-column statistics of a csv file")]
+    Stats(CsvStatsOpts),
+
+    #[command(name = "join", about = "Join two csv files on key columns")]
+    Join(CsvJoinOpts),
+
+    #[command(name = "sort", about = "Sort csv rows by columns")]
+    Sort(CsvSortOpts),
+
+    #[command(name = "dedupe", about = "Remove duplicate csv rows")]
+    Dedupe(CsvDedupeOpts),
+
+    #[command(name = "group", about = "Group csv rows and aggregate columns")]
+    Group(CsvGroupOpts),
+
+    #[command(name = "query", about = "Run a SQL query over csv files")]
+    Query(CsvQueryOpts),
+
+    #[command(name = "split", about = "Split a csv file into chunk files")]
+    Split(CsvSplitOpts),
+
+    #[command(name = "cat", about = "Concatenate csv files")]
+    Cat(CsvCatOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvReaderOpts {
+    #[arg(short, long, value_parser = verify_ascii_char, default_value = ",")]
+    pub delimiter: char,
+
+    #[arg(long, value_parser = verify_ascii_char, default_value = "\"")]
+    pub quote: char,
+
+    #[arg(long, value_parser = verify_ascii_char)]
+    pub escape: Option<char>,
+
+    #[arg(long, value_parser = verify_ascii_char)]
+    pub comment: Option<char>,
+
+    #[arg(long, default_value_t = false)]
+    pub flexible: bool,
+
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    pub header: bool,
+
+    // 不指定时根据 BOM 或内容自动检测
+    #[arg(long, value_parser = verify_encoding)]
+    pub encoding: Option<&'static Encoding>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvConvertOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
 
     #[arg(short, long)]
@@ -17,21 +99,505 @@ pub struct CsvOpts {
     #[arg(long, value_parser = verify_format, default_value = "json")]
     pub format: OutputFormat,
 
-    #[arg(short, long, default_value_t = ',')]
+    // 未指定时根据文件扩展名判断, 默认为 csv
+    #[arg(long, value_parser = verify_source_format)]
+    pub input_format: Option<SourceFormat>,
+
+    #[arg(long)]
+    pub sheet: Option<String>,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+
+    #[arg(long, default_value_t = false)]
+    pub infer: bool,
+
+    #[arg(long, default_value_t = 100)]
+    pub sample_rows: usize,
+
+    #[arg(long, value_parser = verify_file, conflicts_with = "infer")]
+    pub schema: Option<String>,
+
+    #[arg(long, value_delimiter = ',')]
+    pub select: Vec<String>,
+
+    #[arg(long, value_parser = verify_rename)]
+    pub rename: Vec<(String, String)>,
+
+    #[arg(long = "where", value_parser = verify_filter)]
+    pub filter: Option<RowFilter>,
+
+    #[arg(long, default_value_t = false)]
+    pub unflatten: bool,
+
+    #[arg(long, value_parser = verify_encoding)]
+    pub output_encoding: Option<&'static Encoding>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvReverseOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[arg(long, value_parser = verify_input_format, default_value = "json")]
+    pub format: InputFormat,
+
+    #[arg(long, value_parser = verify_column_order, default_value = "first-seen")]
+    pub order: ColumnOrder,
+
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    #[arg(short, long, value_parser = verify_ascii_char, default_value = ",")]
     pub delimiter: char,
 
-    #[arg(long, default_value_t = true)]
-    pub header: bool,
+    #[arg(long, value_parser = verify_encoding)]
+    pub output_encoding: Option<&'static Encoding>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvValidateOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(short, long, value_parser = verify_file)]
+    pub schema: String,
+
+    #[arg(long, default_value_t = 100)]
+    pub max_errors: usize,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvStatsOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+
+    // 不指定时输出文本表格
+    #[arg(long, value_parser = verify_format)]
+    pub format: Option<OutputFormat>,
+
+    #[arg(long, default_value_t = 5)]
+    pub top: usize,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvJoinOpts {
+    #[arg(long, value_parser = verify_file)]
+    pub left: String,
+
+    #[arg(long, value_parser = verify_file)]
+    pub right: String,
+
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub on: Vec<String>,
+
+    #[arg(long, value_parser = verify_join_mode, default_value = "inner")]
+    pub mode: JoinMode,
+
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[arg(long, value_parser = verify_format, default_value = "csv")]
+    pub format: OutputFormat,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+
+    #[arg(long, value_parser = verify_encoding)]
+    pub output_encoding: Option<&'static Encoding>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvSortOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[arg(long, value_parser = verify_format, default_value = "csv")]
+    pub format: OutputFormat,
+
+    // col[:desc], 多个列用逗号分隔
+    #[arg(long, value_parser = verify_sort_key, value_delimiter = ',', required = true)]
+    pub by: Vec<SortKey>,
+
+    // 超过该行数时分块排序并写入临时文件
+    #[arg(long, default_value_t = 100_000)]
+    pub chunk_rows: usize,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+
+    #[arg(long, value_parser = verify_encoding)]
+    pub output_encoding: Option<&'static Encoding>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvDedupeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[arg(long, value_parser = verify_format, default_value = "csv")]
+    pub format: OutputFormat,
+
+    // 不指定时按整行去重
+    #[arg(long, value_delimiter = ',')]
+    pub on: Vec<String>,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+
+    #[arg(long, value_parser = verify_encoding)]
+    pub output_encoding: Option<&'static Encoding>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvGroupOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[arg(long, value_parser = verify_format, default_value = "csv")]
+    pub format: OutputFormat,
+
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub by: Vec<String>,
+
+    // count(), count(x), sum(x), mean(x), min(x), max(x)
+    #[arg(long, value_parser = verify_aggregate, value_delimiter = ',', required = true)]
+    pub agg: Vec<Aggregate>,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+
+    #[arg(long, value_parser = verify_encoding)]
+    pub output_encoding: Option<&'static Encoding>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvQueryOpts {
+    // 表名为输入文件去掉扩展名, 如 assets/juventus.csv 对应 juventus
+    pub sql: String,
+
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: Vec<String>,
+
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[arg(long, value_parser = verify_format, default_value = "csv")]
+    pub format: OutputFormat,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+
+    #[arg(long, value_parser = verify_encoding)]
+    pub output_encoding: Option<&'static Encoding>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvSplitOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(long, required_unless_present = "by", conflicts_with = "by")]
+    pub rows: Option<usize>,
+
+    // 按列值拆分, 每个值一个文件
+    #[arg(long)]
+    pub by: Option<String>,
+
+    #[arg(long, value_parser = verify_path, default_value = ".")]
+    pub output_dir: PathBuf,
+
+    // 默认为输入文件名
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvCatOpts {
+    #[arg(value_parser = verify_file, required = true)]
+    pub inputs: Vec<String>,
+
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[command(flatten)]
+    pub reader: CsvReaderOpts,
+
+    #[arg(long, value_parser = verify_encoding)]
+    pub output_encoding: Option<&'static Encoding>,
+}
+
+impl From<&CsvReaderOpts> for CsvReaderConfig {
+    fn from(opts: &CsvReaderOpts) -> Self {
+        // verify_ascii_char 已保证均为 ASCII 字符
+        CsvReaderConfig {
+            delimiter: opts.delimiter as u8,
+            quote: opts.quote as u8,
+            escape: opts.escape.map(|c| c as u8),
+            comment: opts.comment.map(|c| c as u8),
+            flexible: opts.flexible,
+            has_headers: opts.header,
+            encoding: opts.encoding,
+        }
+    }
+}
+
+impl CsvConvertOpts {
+    fn transform(&self) -> Result<CsvTransform> {
+        let inference = match &self.schema {
+            Some(schema) => Inference::load_schema(schema)?,
+            None if self.infer => Inference::Sample(self.sample_rows),
+            None => Inference::None,
+        };
+        Ok(CsvTransform {
+            inference,
+            filter: self.filter.clone(),
+            select: self.select.clone(),
+            rename: self.rename.clone(),
+            unflatten: self.unflatten,
+        })
+    }
+
+    // xlsx 和 parquet 自带类型且不经过 csv 解析, 这些选项对它们没有意义
+    fn csv_only_flags(&self, format: SourceFormat) -> Vec<&'static str> {
+        if matches!(format, SourceFormat::Csv) {
+            return Vec::new();
+        }
+        let default = CsvReaderConfig::default();
+        let config = CsvReaderConfig::from(&self.reader);
+        [
+            ("--delimiter", config.delimiter != default.delimiter),
+            ("--quote", config.quote != default.quote),
+            ("--escape", config.escape.is_some()),
+            ("--comment", config.comment.is_some()),
+            ("--flexible", config.flexible),
+            ("--encoding", config.encoding.is_some()),
+            (
+                "--header",
+                matches!(format, SourceFormat::Parquet) && !config.has_headers,
+            ),
+            ("--infer", self.infer),
+            ("--schema", self.schema.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(flag, set)| set.then_some(flag))
+        .collect()
+    }
+
+    fn source(&self) -> Result<TableSource> {
+        let format = self
+            .input_format
+            .unwrap_or_else(|| SourceFormat::detect(&self.input));
+        if self.sheet.is_some() && !matches!(format, SourceFormat::Xlsx) {
+            anyhow::bail!("--sheet only applies to xlsx input");
+        }
+        let flags = self.csv_only_flags(format);
+        if !flags.is_empty() {
+            let verb = if flags.len() == 1 { "applies" } else { "apply" };
+            anyhow::bail!("{} only {} to csv input", flags.join(", "), verb);
+        }
+        let source = match format {
+            SourceFormat::Csv => TableSource::Csv(CsvReaderConfig::from(&self.reader)),
+            SourceFormat::Xlsx => TableSource::Xlsx {
+                sheet: self.sheet.clone(),
+                has_headers: self.reader.header,
+            },
+            SourceFormat::Parquet => TableSource::Parquet,
+        };
+        Ok(source)
+    }
 }
 
 impl CmdExecutor for CsvOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let output: String = if let Some(output) = self.output {
-            output
-        } else {
-            format!("output.{}", self.format)
+        match self.cmd {
+            Some(cmd) => cmd.execute().await,
+            None => self.convert.execute().await,
+        }
+    }
+}
+
+impl CmdExecutor for CsvConvertOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let source = self.source()?;
+        let transform = self.transform()?;
+        let output = output_path(self.output, self.format);
+        let writer = encode_writer(&output, self.format, self.output_encoding)?;
+        process_csv(&self.input, writer, self.format, &source, &transform)
+    }
+}
+
+impl CmdExecutor for CsvReverseOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let output = output_path(self.output, OutputFormat::Csv);
+        let writer = encode_writer(&output, OutputFormat::Csv, self.output_encoding)?;
+        process_csv_reverse(
+            &self.input,
+            writer,
+            self.format,
+            self.order,
+            &self.columns,
+            self.delimiter as u8,
+        )
+    }
+}
+
+impl CmdExecutor for CsvValidateOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let config = CsvReaderConfig::from(&self.reader);
+        let spec = ValidationSpec::load(&self.schema)?;
+        let report = process_csv_validate(&self.input, &config, &spec, self.max_errors)?;
+        for failure in &report.failures {
+            println!("{}", failure);
+        }
+        if !report.missing_columns.is_empty() {
+            anyhow::bail!(
+                "Missing columns {:?}, {} of {} rows failed validation",
+                report.missing_columns,
+                report.invalid_rows,
+                report.rows
+            );
+        }
+        if !report.is_valid() {
+            anyhow::bail!(
+                "{} of {} rows failed validation",
+                report.invalid_rows,
+                report.rows
+            );
+        }
+        println!("All {} rows are valid", report.rows);
+        Ok(())
+    }
+}
+
+impl CmdExecutor for CsvStatsOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let config = CsvReaderConfig::from(&self.reader);
+        let writer = write_data(&self.output)?;
+        process_csv_stats(&self.input, writer, &config, self.top, self.format)
+    }
+}
+
+impl CmdExecutor for CsvJoinOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let config = CsvReaderConfig::from(&self.reader);
+        let output = output_path(self.output, self.format);
+        let writer = encode_writer(&output, self.format, self.output_encoding)?;
+        process_csv_join(
+            &self.left,
+            &self.right,
+            writer,
+            &self.on,
+            self.mode,
+            &config,
+            self.format,
+        )
+    }
+}
+
+impl CmdExecutor for CsvSortOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let config = CsvReaderConfig::from(&self.reader);
+        let output = output_path(self.output, self.format);
+        let writer = encode_writer(&output, self.format, self.output_encoding)?;
+        process_csv_sort(
+            &self.input,
+            writer,
+            &self.by,
+            self.chunk_rows,
+            &config,
+            self.format,
+        )
+    }
+}
+
+impl CmdExecutor for CsvDedupeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let config = CsvReaderConfig::from(&self.reader);
+        let output = output_path(self.output, self.format);
+        let writer = encode_writer(&output, self.format, self.output_encoding)?;
+        process_csv_dedupe(&self.input, writer, &self.on, &config, self.format)
+    }
+}
+
+impl CmdExecutor for CsvGroupOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let config = CsvReaderConfig::from(&self.reader);
+        let output = output_path(self.output, self.format);
+        let writer = encode_writer(&output, self.format, self.output_encoding)?;
+        process_csv_group(
+            &self.input,
+            writer,
+            &self.by,
+            &self.agg,
+            &config,
+            self.format,
+        )
+    }
+}
+
+impl CmdExecutor for CsvQueryOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let config = CsvReaderConfig::from(&self.reader);
+        let output = output_path(self.output, self.format);
+        let writer = encode_writer(&output, self.format, self.output_encoding)?;
+        process_csv_query(&self.sql, &self.input, writer, &config, self.format)
+    }
+}
+
+impl CmdExecutor for CsvSplitOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let config = CsvReaderConfig::from(&self.reader);
+        let by = match (self.rows, self.by) {
+            (Some(rows), _) => SplitBy::Rows(rows),
+            (None, Some(column)) => SplitBy::Column(column),
+            // clap 已保证二者必有其一
+            (None, None) => unreachable!(),
         };
-        process_csv(&self.input, output, self.format)
+        let files = process_csv_split(
+            &self.input,
+            &self.output_dir,
+            self.prefix.as_deref(),
+            &by,
+            &config,
+        )?;
+        for file in files {
+            println!("{}", file.display());
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for CsvCatOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let config = CsvReaderConfig::from(&self.reader);
+        let output = output_path(self.output, OutputFormat::Csv);
+        let writer = encode_writer(&output, OutputFormat::Csv, self.output_encoding)?;
+        process_csv_cat(&self.inputs, writer, &config)
     }
 }
 
@@ -39,6 +605,81 @@ impl CmdExecutor for CsvOpts {
 pub enum OutputFormat {
     Json,
     Yaml,
+    Ndjson,
+    Toml,
+    Xml,
+    Csv,
+}
+
+// 未指定输出且 stdout 不是终端时(如管道)写到 stdout, 否则写到 output.<format>
+fn output_path(output: Option<String>, format: OutputFormat) -> String {
+    match output {
+        Some(output) => output,
+        None if !io::stdout().is_terminal() => "-".into(),
+        None => format!("output.{}", format),
+    }
+}
+
+// XML 声明固定为 UTF-8, 转码后声明与内容不一致, 在创建输出文件之前拒绝
+fn encode_writer(
+    output: &str,
+    format: OutputFormat,
+    encoding: Option<&'static Encoding>,
+) -> Result<Box<dyn Write>> {
+    let encoding = encoding.filter(|e| *e != encoding_rs::UTF_8);
+    if matches!(format, OutputFormat::Xml) && encoding.is_some() {
+        anyhow::bail!("--format xml only supports utf-8 --output-encoding");
+    }
+    let writer = write_data(output)?;
+    Ok(match encoding {
+        Some(encoding) => Box::new(EncodeWriter::new(writer, encoding)),
+        None => writer,
+    })
+}
+
+// 支持 `\t` 写法, 便于在命令行中指定 TSV 分隔符
+pub fn verify_ascii_char(input: &str) -> Result<char, anyhow::Error> {
+    let c = match input {
+        "\\t" => '\t',
+        _ => {
+            let mut chars = input.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => anyhow::bail!("Expect a single character, got {:?}", input),
+            }
+        }
+    };
+    if !c.is_ascii() {
+        anyhow::bail!("Only ASCII characters are supported, got {:?}", c);
+    }
+    Ok(c)
+}
+
+pub fn verify_rename(input: &str) -> Result<(String, String), anyhow::Error> {
+    match input.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => Ok((old.into(), new.into())),
+        _ => anyhow::bail!("Expect old=new, got {:?}", input),
+    }
+}
+
+// 接受 WHATWG 编码标签, 如 gbk, utf-16le, windows-1252
+pub fn verify_encoding(label: &str) -> Result<&'static Encoding, anyhow::Error> {
+    match Encoding::for_label(label.as_bytes()) {
+        Some(encoding) => Ok(encoding),
+        None => anyhow::bail!("Unsupported encoding {:?}", label),
+    }
+}
+
+pub fn verify_sort_key(key: &str) -> Result<SortKey, anyhow::Error> {
+    key.parse()
+}
+
+pub fn verify_aggregate(agg: &str) -> Result<Aggregate, anyhow::Error> {
+    agg.parse()
+}
+
+pub fn verify_filter(expr: &str) -> Result<RowFilter, anyhow::Error> {
+    expr.parse()
 }
 
 pub fn verify_format(ft: &str) -> Result<OutputFormat, anyhow::Error> {
@@ -50,6 +691,10 @@ impl From<OutputFormat> for &'static str {
         match format {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Toml => "toml",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Csv => "csv",
         }
     }
 }
@@ -61,6 +706,10 @@ impl FromStr for OutputFormat {
         match format.to_lowercase().as_str() {
             "json" => Ok(OutputFormat::Json),
             "yaml" => Ok(OutputFormat::Yaml),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "toml" => Ok(OutputFormat::Toml),
+            "xml" => Ok(OutputFormat::Xml),
+            "csv" => Ok(OutputFormat::Csv),
             v => anyhow::bail!("Unsupported format {:?}", v),
         }
     }
@@ -71,3 +720,237 @@ impl fmt::Display for OutputFormat {
         write!(fmt, "{}", Into::<&str>::into(*self)) // TODO: 找时间继续研究 From与Into
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+pub enum InputFormat {
+    Json,
+    Ndjson,
+    Yaml,
+}
+
+pub fn verify_input_format(ft: &str) -> Result<InputFormat, anyhow::Error> {
+    ft.parse()
+}
+
+impl From<InputFormat> for &'static str {
+    fn from(format: InputFormat) -> Self {
+        match format {
+            InputFormat::Json => "json",
+            InputFormat::Ndjson => "ndjson",
+            InputFormat::Yaml => "yaml",
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(InputFormat::Json),
+            "ndjson" => Ok(InputFormat::Ndjson),
+            "yaml" => Ok(InputFormat::Yaml),
+            v => anyhow::bail!("Unsupported format {:?}", v),
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ColumnOrder {
+    FirstSeen,
+    Sorted,
+}
+
+pub fn verify_column_order(order: &str) -> Result<ColumnOrder, anyhow::Error> {
+    order.parse()
+}
+
+impl From<ColumnOrder> for &'static str {
+    fn from(order: ColumnOrder) -> Self {
+        match order {
+            ColumnOrder::FirstSeen => "first-seen",
+            ColumnOrder::Sorted => "sorted",
+        }
+    }
+}
+
+impl FromStr for ColumnOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(order: &str) -> Result<Self, Self::Err> {
+        match order.to_lowercase().as_str() {
+            "first-seen" => Ok(ColumnOrder::FirstSeen),
+            "sorted" => Ok(ColumnOrder::Sorted),
+            v => anyhow::bail!("Unsupported column order {:?}", v),
+        }
+    }
+}
+
+impl fmt::Display for ColumnOrder {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinMode {
+    Inner,
+    Left,
+    Outer,
+}
+
+pub fn verify_join_mode(mode: &str) -> Result<JoinMode, anyhow::Error> {
+    mode.parse()
+}
+
+impl From<JoinMode> for &'static str {
+    fn from(mode: JoinMode) -> Self {
+        match mode {
+            JoinMode::Inner => "inner",
+            JoinMode::Left => "left",
+            JoinMode::Outer => "outer",
+        }
+    }
+}
+
+impl FromStr for JoinMode {
+    type Err = anyhow::Error;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.to_lowercase().as_str() {
+            "inner" => Ok(JoinMode::Inner),
+            "left" => Ok(JoinMode::Left),
+            "outer" => Ok(JoinMode::Outer),
+            v => anyhow::bail!("Unsupported join mode {:?}", v),
+        }
+    }
+}
+
+impl fmt::Display for JoinMode {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SourceFormat {
+    Csv,
+    Xlsx,
+    Parquet,
+}
+
+pub fn verify_source_format(format: &str) -> Result<SourceFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl SourceFormat {
+    fn detect(input: &str) -> Self {
+        let extension = input.rsplit_once('.').map(|(_, ext)| ext.to_lowercase());
+        match extension.as_deref() {
+            Some("xlsx" | "xlsm" | "xlsb" | "xls" | "ods") => SourceFormat::Xlsx,
+            Some("parquet") => SourceFormat::Parquet,
+            _ => SourceFormat::Csv,
+        }
+    }
+}
+
+impl From<SourceFormat> for &'static str {
+    fn from(format: SourceFormat) -> Self {
+        match format {
+            SourceFormat::Csv => "csv",
+            SourceFormat::Xlsx => "xlsx",
+            SourceFormat::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for SourceFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(SourceFormat::Csv),
+            "xlsx" => Ok(SourceFormat::Xlsx),
+            "parquet" => Ok(SourceFormat::Parquet),
+            v => anyhow::bail!("Unsupported input format {:?}", v),
+        }
+    }
+}
+
+impl fmt::Display for SourceFormat {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_ascii_char() {
+        assert_eq!(verify_ascii_char(";").unwrap(), ';');
+        assert_eq!(verify_ascii_char("\\t").unwrap(), '\t');
+        assert!(verify_ascii_char("ab").is_err());
+        assert!(verify_ascii_char("；").is_err());
+    }
+
+    #[test]
+    fn test_verify_rename() {
+        assert_eq!(
+            verify_rename("Kit Number=kit").unwrap(),
+            ("Kit Number".into(), "kit".into())
+        );
+        assert!(verify_rename("kit").is_err());
+    }
+
+    #[test]
+    fn test_verify_encoding() {
+        assert_eq!(verify_encoding("GBK").unwrap(), encoding_rs::GBK);
+        assert_eq!(verify_encoding("utf-16le").unwrap(), encoding_rs::UTF_16LE);
+        assert!(verify_encoding("klingon").is_err());
+    }
+
+    #[test]
+    fn test_encode_writer_rejects_non_utf8_xml() {
+        let gbk = Some(encoding_rs::GBK);
+        assert!(encode_writer("-", OutputFormat::Xml, gbk).is_err());
+        assert!(encode_writer("-", OutputFormat::Xml, Some(encoding_rs::UTF_8)).is_ok());
+        assert!(encode_writer("-", OutputFormat::Csv, gbk).is_ok());
+    }
+
+    #[test]
+    fn test_output_format_round_trip() {
+        for ft in ["json", "yaml", "ndjson", "toml", "xml", "csv"] {
+            assert_eq!(verify_format(ft).unwrap().to_string(), ft);
+        }
+        assert!(verify_format("xlsx").is_err());
+    }
+
+    #[test]
+    fn test_convert_source_rejects_csv_flags() {
+        let source = |args: &[&str]| {
+            let args = ["csv", "-i", "assets/juventus.xlsx"].iter().chain(args);
+            CsvConvertOpts::try_parse_from(args).unwrap().source()
+        };
+        assert!(source(&["--sheet", "Players", "--header", "false"]).is_ok());
+        for args in [
+            &["--delimiter", ";"][..],
+            &["--encoding", "gbk"],
+            &["--flexible"],
+            &["--infer"],
+            &["--schema", "assets/juventus.csv"],
+        ] {
+            let err = source(args).unwrap_err().to_string();
+            assert!(err.starts_with(args[0]), "{}", err);
+        }
+        let err = source(&["--input-format", "parquet", "--header", "false"]).unwrap_err();
+        assert_eq!(err.to_string(), "--header only applies to csv input");
+    }
+}
@@ -1,38 +1,303 @@
-use clap::Parser;
+use anyhow::Result;
+use clap::{ArgAction, Parser};
+use enum_dispatch::enum_dispatch;
+use std::{
+    fmt,
+    io::{self, IsTerminal},
+    str::FromStr,
+};
 
-use crate::{process_genpass, CmdExecutor};
+use super::verify_file;
+use crate::{
+    passphrase_entropy, password_entropy, pattern_entropy, process_genpass, process_genpass_derive,
+    process_genpass_policy, process_genpattern, process_genphrase, pronounceable_pattern,
+    write_passwords, CmdExecutor, PasswordPolicy, PasswordReport, PasswordSpec, SecureRng,
+};
 
+// 不带子命令时保持 `rcli genpass ...` 的随机生成行为
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct GenPassOpts {
+    #[command(subcommand)]
+    pub cmd: Option<GenPassSubCommand>,
+
+    #[command(flatten)]
+    pub generate: GenPassGenerateOpts,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum GenPassSubCommand {
+    #[command(
+        name = "derive",
+        about = "Derive a stable password from a master passphrase"
+    )]
+    Derive(GenPassDeriveOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct PasswordClassOpts {
     #[arg(short, long, default_value_t = 16)]
     pub length: u8,
 
-    #[arg(long, default_value_t = true)]
+    #[arg(long = "no-uppercase", action = ArgAction::SetFalse)]
     pub uppercase: bool,
 
-    #[arg(long, default_value_t = true)]
+    #[arg(long = "no-lowercase", action = ArgAction::SetFalse)]
     pub lowercase: bool,
 
-    #[arg(long, default_value_t = true)]
+    #[arg(long = "no-number", action = ArgAction::SetFalse)]
     pub number: bool,
 
-    #[arg(long, default_value_t = true)]
+    #[arg(long = "no-symbol", action = ArgAction::SetFalse)]
     pub symbol: bool,
+
+    // 启用的字符类默认至少出现一次
+    #[arg(long, conflicts_with = "uppercase")]
+    pub min_uppercase: Option<u8>,
+
+    #[arg(long, conflicts_with = "lowercase")]
+    pub min_lowercase: Option<u8>,
+
+    #[arg(long, conflicts_with = "number")]
+    pub min_number: Option<u8>,
+
+    #[arg(long, conflicts_with = "symbol")]
+    pub min_symbol: Option<u8>,
+
+    #[arg(
+        long,
+        default_value = "!@#$%^&*_",
+        conflicts_with = "symbol",
+        allow_hyphen_values = true
+    )]
+    pub symbols: String,
+
+    #[arg(long, default_value = "", allow_hyphen_values = true)]
+    pub exclude: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassGenerateOpts {
+    #[command(flatten)]
+    pub classes: PasswordClassOpts,
+
+    // 指定单词数时生成 diceware 口令, 忽略字符相关选项
+    #[arg(short, long, conflicts_with = "length")]
+    pub words: Option<u8>,
+
+    #[arg(long, default_value = "-", requires = "words")]
+    pub separator: String,
+
+    #[arg(long, default_value_t = false, requires = "words")]
+    pub capitalize: bool,
+
+    #[arg(long, default_value_t = false, requires = "words")]
+    pub digit: bool,
+
+    // 按策略文件生成, 忽略其他字符相关选项
+    #[arg(
+        long,
+        value_parser = verify_file,
+        conflicts_with_all = [
+            "length", "uppercase", "lowercase", "number", "symbol", "min_uppercase",
+            "min_lowercase", "min_number", "min_symbol", "symbols", "exclude", "words",
+        ]
+    )]
+    pub policy: Option<String>,
+
+    // 辅音元音交替的可朗读密码, 长度取 --length
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = [
+            "uppercase", "lowercase", "number", "symbol", "min_uppercase", "min_lowercase",
+            "min_number", "min_symbol", "symbols", "exclude", "words", "policy",
+        ]
+    )]
+    pub pronounceable: bool,
+
+    // 模板如 "Cvccvc-999-!!": C/c 辅音, V/v 元音, A/a 字母, 9 数字, ! 符号, `\` 转义
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        conflicts_with_all = [
+            "length", "uppercase", "lowercase", "number", "symbol", "min_uppercase",
+            "min_lowercase", "min_number", "min_symbol", "symbols", "exclude", "words", "policy",
+            "pronounceable",
+        ]
+    )]
+    pub pattern: Option<String>,
+
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub count: u32,
+
+    #[arg(long, value_parser = verify_genpass_format, default_value = "lines")]
+    pub format: GenPassFormat,
+
+    // 固定随机种子, 输出可预测, 仅用于生成测试数据
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+// 主密码从终端读取(不回显), stdin 不是终端时读取第一行
+#[derive(Debug, Parser)]
+pub struct GenPassDeriveOpts {
+    #[arg(long)]
+    pub site: String,
+
+    #[arg(long)]
+    pub login: String,
+
+    #[arg(long, default_value_t = 1)]
+    pub counter: u32,
+
+    #[command(flatten)]
+    pub classes: PasswordClassOpts,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum GenPassFormat {
+    Lines,
+    Json,
+    Csv,
+}
+
+impl PasswordClassOpts {
+    fn spec(&self) -> PasswordSpec {
+        let class = |enabled: bool, min: Option<u8>| enabled.then(|| min.unwrap_or(1));
+        PasswordSpec {
+            length: self.length,
+            uppercase: class(self.uppercase, self.min_uppercase),
+            lowercase: class(self.lowercase, self.min_lowercase),
+            number: class(self.number, self.min_number),
+            symbol: class(self.symbol, self.min_symbol),
+            symbols: self.symbols.clone(),
+            exclude: self.exclude.clone(),
+        }
+    }
+}
+
+impl GenPassGenerateOpts {
+    fn generate(
+        &self,
+        spec: &PasswordSpec,
+        policy: Option<&PasswordPolicy>,
+        rng: &mut SecureRng,
+    ) -> Result<PasswordReport> {
+        let pattern = match &self.pattern {
+            Some(pattern) => Some(pattern.clone()),
+            None if self.pronounceable => Some(pronounceable_pattern(self.classes.length)),
+            None => None,
+        };
+        let (pass, entropy) = match (self.words, pattern, policy) {
+            (Some(words), _, _) => (
+                process_genphrase(words, &self.separator, self.capitalize, self.digit, rng)?,
+                passphrase_entropy(words, self.digit),
+            ),
+            (None, Some(pattern), _) => (
+                process_genpattern(&pattern, rng)?,
+                pattern_entropy(&pattern)?,
+            ),
+            (None, None, Some(policy)) => {
+                let pass = process_genpass_policy(policy, rng)?;
+                let entropy = password_entropy(&policy.spec(pass.len() as u8))?;
+                (pass, entropy)
+            }
+            (None, None, None) => (process_genpass(spec, rng)?, password_entropy(spec)?),
+        };
+        PasswordReport::new(pass, entropy)
+    }
 }
 
 impl CmdExecutor for GenPassOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let pass = process_genpass(
-            self.length,
-            self.uppercase,
-            self.lowercase,
-            self.number,
-            self.symbol,
+        match self.cmd {
+            Some(cmd) => cmd.execute().await,
+            None => self.generate.execute().await,
+        }
+    }
+}
+
+impl CmdExecutor for GenPassGenerateOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        if self.pronounceable && self.classes.length == 0 {
+            anyhow::bail!("--length must be greater than 0 for --pronounceable");
+        }
+        let spec = self.classes.spec();
+        let policy = self.policy.as_ref().map(PasswordPolicy::load).transpose()?;
+        if self.seed.is_some() {
+            eprintln!("warning: --seed makes passwords predictable, use it for test fixtures only");
+        }
+        let mut rng = SecureRng::new(self.seed);
+        // 只生成一个密码时保持原有行为, 在 stderr 输出强度
+        if self.count == 1 && matches!(self.format, GenPassFormat::Lines) {
+            let report = self.generate(&spec, policy.as_ref(), &mut rng)?;
+            println!("{}", report.password);
+            eprintln!(
+                "score {}, entropy {:.1} bits, crack time {}",
+                report.score, report.entropy, report.crack_time
+            );
+            return Ok(());
+        }
+        let reports = (0..self.count).map(|_| self.generate(&spec, policy.as_ref(), &mut rng));
+        write_passwords(reports, io::stdout().lock(), self.format)
+    }
+}
+
+impl CmdExecutor for GenPassDeriveOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let master = read_master()?;
+        let pass = process_genpass_derive(
+            &master,
+            &self.site,
+            &self.login,
+            self.counter,
+            &self.classes.spec(),
         )?;
         println!("{}", pass);
-
-        let estimate = zxcvbn::zxcvbn(&pass, &[])?;
-        eprint!("score {}", estimate.score());
         Ok(())
     }
 }
+
+fn read_master() -> Result<String> {
+    if io::stdin().is_terminal() {
+        return Ok(rpassword::prompt_password("Master passphrase: ")?);
+    }
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+pub fn verify_genpass_format(format: &str) -> Result<GenPassFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl From<GenPassFormat> for &'static str {
+    fn from(format: GenPassFormat) -> Self {
+        match format {
+            GenPassFormat::Lines => "lines",
+            GenPassFormat::Json => "json",
+            GenPassFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for GenPassFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "lines" => Ok(GenPassFormat::Lines),
+            "json" => Ok(GenPassFormat::Json),
+            "csv" => Ok(GenPassFormat::Csv),
+            v => anyhow::bail!("Unsupported format {:?}, expect lines, json or csv", v),
+        }
+    }
+}
+
+impl fmt::Display for GenPassFormat {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", Into::<&str>::into(*self))
+    }
+}
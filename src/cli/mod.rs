@@ -3,6 +3,7 @@ mod csv;
 mod genpass;
 mod http;
 mod jwt;
+mod passcheck;
 mod text;
 
 use clap::Parser;
@@ -11,10 +12,18 @@ use std::path::{Path, PathBuf};
 
 pub use self::{
     base64::{Base64DecodeOpts, Base64EncodeOpts, Base64Format, Base64SubCommand},
-    csv::{CsvOpts, OutputFormat},
-    genpass::GenPassOpts,
+    csv::{
+        ColumnOrder, CsvCatOpts, CsvConvertOpts, CsvDedupeOpts, CsvGroupOpts, CsvJoinOpts, CsvOpts,
+        CsvQueryOpts, CsvReaderOpts, CsvReverseOpts, CsvSortOpts, CsvSplitOpts, CsvStatsOpts,
+        CsvSubCommand, CsvValidateOpts, InputFormat, JoinMode, OutputFormat, SourceFormat,
+    },
+    genpass::{
+        GenPassDeriveOpts, GenPassFormat, GenPassGenerateOpts, GenPassOpts, GenPassSubCommand,
+        PasswordClassOpts,
+    },
     http::{HttpSubCommand, ServeOpts},
     jwt::{JwtSignOpts, JwtSubCommand, JwtVerifyOpts},
+    passcheck::{PassCheckFormat, PassCheckOpts},
     text::{
         DecryptOpts, EncryptOpts, GenerateOpts, TextEncryptFormat, TextSignFormat, TextSignOpts,
         TextSubCommand, TextVerifyOpts,
@@ -28,6 +37,8 @@ pub struct Opts {
     pub cmd: SubCommand,
 }
 
+// 命令行参数只解析一次, 不必为变体大小装箱
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Parser)]
 #[enum_dispatch(CmdExecutor)]
 pub enum SubCommand {
@@ -37,6 +48,9 @@ pub enum SubCommand {
     #[command(name = "genpass", about = "Generate a random password")]
     GenPass(GenPassOpts),
 
+    #[command(name = "passcheck", about = "Check password strength")]
+    PassCheck(PassCheckOpts),
+
     #[command(subcommand, about = "Base64 encode/decode")]
     Base64(Base64SubCommand),
 
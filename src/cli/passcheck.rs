@@ -0,0 +1,154 @@
+use anyhow::Result;
+use clap::Parser;
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use super::verify_file;
+use crate::{process_passcheck, CmdExecutor, PasswordCheck};
+
+#[derive(Debug, Parser)]
+pub struct PassCheckOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    // 任一密码低于该分数时以非零状态退出
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub min_score: u8,
+
+    #[arg(long, value_parser = verify_passcheck_format, default_value = "text")]
+    pub format: PassCheckFormat,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PassCheckFormat {
+    Text,
+    Json,
+}
+
+impl CmdExecutor for PassCheckOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let checks = process_passcheck(&self.input)?;
+        write_checks(&checks, io::stdout().lock(), self.format)?;
+
+        let weak = checks.iter().filter(|c| c.score < self.min_score).count();
+        if weak > 0 {
+            anyhow::bail!(
+                "{} of {} passwords scored below {}",
+                weak,
+                checks.len(),
+                self.min_score
+            );
+        }
+        Ok(())
+    }
+}
+
+fn write_checks(
+    checks: &[PasswordCheck],
+    mut writer: impl Write,
+    format: PassCheckFormat,
+) -> Result<()> {
+    match format {
+        PassCheckFormat::Text => {
+            for check in checks {
+                write_check(check, &mut writer)?;
+            }
+        }
+        PassCheckFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, checks)?;
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+// 模式只输出类型和位置, 暴力匹配的片段可能就是整个密码
+fn write_check(check: &PasswordCheck, writer: &mut impl Write) -> Result<()> {
+    writeln!(
+        writer,
+        "line {}: score {}/4, guesses 10^{:.1}, offline crack time {} (slow hash) / {} (fast hash)",
+        check.line,
+        check.score,
+        check.guesses_log10,
+        check.crack_times.offline_slow_hash,
+        check.crack_times.offline_fast_hash
+    )?;
+    let patterns = check
+        .patterns
+        .iter()
+        .map(|p| format!("{} {}..{} ({} chars)", p.pattern, p.start, p.end, p.length))
+        .collect::<Vec<_>>();
+    writeln!(writer, "  patterns: {}", patterns.join(", "))?;
+    if let Some(warning) = &check.warning {
+        writeln!(writer, "  warning: {}", warning)?;
+    }
+    for suggestion in &check.suggestions {
+        writeln!(writer, "  suggestion: {}", suggestion)?;
+    }
+    Ok(())
+}
+
+pub fn verify_passcheck_format(format: &str) -> Result<PassCheckFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl From<PassCheckFormat> for &'static str {
+    fn from(format: PassCheckFormat) -> Self {
+        match format {
+            PassCheckFormat::Text => "text",
+            PassCheckFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for PassCheckFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "text" => Ok(PassCheckFormat::Text),
+            "json" => Ok(PassCheckFormat::Json),
+            v => anyhow::bail!("Unsupported format {:?}, expect text or json", v),
+        }
+    }
+}
+
+impl fmt::Display for PassCheckFormat {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_checks_hides_passwords() -> Result<()> {
+        let passwords = [
+            "password123",
+            "Tr0ub4dour&3",
+            "x7#Vq!9zLp@2mK",
+            "qwertyuiop",
+        ];
+        let checks = process_passcheck(&passwords.join("\n"))?;
+        for format in [PassCheckFormat::Text, PassCheckFormat::Json] {
+            let mut buf = Vec::new();
+            write_checks(&checks, &mut buf, format)?;
+            let output = String::from_utf8(buf)?;
+            assert!(output.contains("bruteforce"));
+            for password in passwords {
+                assert!(
+                    !output.contains(password),
+                    "{} leaked in {}",
+                    password,
+                    format
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,23 @@
 use clap::Parser;
 use rcli::{CmdExecutor, Opts};
+use std::io;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // tracing初始化
     tracing_subscriber::fmt::init();
     let opts = Opts::parse();
-    opts.cmd.execute().await?;
+    if let Err(e) = opts.cmd.execute().await {
+        // 下游管道提前关闭(如 `| head`)时正常退出
+        let broken_pipe = e.chain().any(|cause| {
+            cause
+                .downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+        });
+        if !broken_pipe {
+            return Err(e);
+        }
+    }
 
     Ok(())
 }
@@ -19,19 +19,37 @@ pub fn process_encode(input: &str, format: Base64Format) -> Result<String> {
     Ok(encoded)
 }
 
-pub fn process_decode(input: &str, format: Base64Format) -> Result<String> {
+// 返回原始字节, 不做 UTF-8 转换; 忽略输入中的空白(如按行折断的 base64)
+pub fn process_decode(input: &str, format: Base64Format) -> Result<Vec<u8>> {
     let mut reader = read_data(input)?;
-    let mut buf = String::new();
-    reader.read_to_string(&mut buf)?;
-    let buf = buf.trim(); // 去除多余的换行符
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    buf.retain(|b| !b.is_ascii_whitespace());
 
     let decoded = match format {
-        Base64Format::Standard => STANDARD.decode(buf)?,
-        Base64Format::UrlSafe => URL_SAFE_NO_PAD.decode(buf)?,
+        Base64Format::Standard => STANDARD.decode(&buf)?,
+        Base64Format::UrlSafe => URL_SAFE_NO_PAD.decode(&buf)?,
     };
 
-    // TODO: decoded data might not be string
-    let decoded_str = String::from_utf8(decoded)?;
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_decode_binary() -> Result<()> {
+        // 非 UTF-8 字节
+        let bytes = [0xff, 0xd8, 0xff, 0x00, 0x80, b'\n'];
+        let encoded = STANDARD.encode(bytes);
+        assert_eq!(process_decode(&encoded, Base64Format::Standard)?, bytes);
+
+        let wrapped = format!("{}\n{}\n", &encoded[..4], &encoded[4..]);
+        assert_eq!(process_decode(&wrapped, Base64Format::Standard)?, bytes);
 
-    Ok(decoded_str)
+        let encoded = URL_SAFE_NO_PAD.encode(bytes);
+        assert_eq!(process_decode(&encoded, Base64Format::UrlSafe)?, bytes);
+        Ok(())
+    }
 }
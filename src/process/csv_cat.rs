@@ -0,0 +1,104 @@
+use anyhow::Result;
+use csv::WriterBuilder;
+use std::io::{Read, Write};
+
+use crate::{read_data, CsvReaderConfig};
+
+pub fn process_csv_cat(
+    inputs: &[String],
+    mut writer: impl Write,
+    config: &CsvReaderConfig,
+) -> Result<()> {
+    cat_records(inputs, read_data, &mut writer, config)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+// 表头取所有文件表头的并集(按首次出现顺序), 缺失的列留空;
+// 先逐个读取表头, 再逐个重新打开输出记录, 同一时刻只打开一个文件
+// stdin 无法重新打开, 第一遍读取表头后保留其 reader
+fn cat_records<R: Read, W: Write>(
+    inputs: &[String],
+    open: impl Fn(&str) -> Result<R>,
+    writer: W,
+    config: &CsvReaderConfig,
+) -> Result<()> {
+    if inputs.iter().filter(|input| *input == "-").count() > 1 {
+        anyhow::bail!("stdin can only be read once");
+    }
+    let mut stdin = None;
+    let mut headers = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let mut reader = config.reader(open(input)?)?;
+        headers.push(config.headers(&mut reader)?);
+        if input == "-" {
+            stdin = Some(reader);
+        }
+    }
+    let mut columns: Vec<&str> = Vec::new();
+    for name in headers.iter().flatten() {
+        if !columns.contains(&name) {
+            columns.push(name);
+        }
+    }
+
+    let mut writer = WriterBuilder::new()
+        .delimiter(config.delimiter)
+        .quote(config.quote)
+        .from_writer(writer);
+    if config.has_headers {
+        writer.write_record(&columns)?;
+    }
+    for (input, headers) in inputs.iter().zip(&headers) {
+        let reader = match stdin.take_if(|_| input == "-") {
+            Some(reader) => reader,
+            None => {
+                let mut reader = config.reader(open(input)?)?;
+                config.headers(&mut reader)?;
+                reader
+            }
+        };
+        let positions = columns
+            .iter()
+            .map(|c| headers.iter().position(|h| h == *c))
+            .collect::<Vec<_>>();
+        for record in reader.into_records() {
+            let record = record?;
+            let fields = positions
+                .iter()
+                .map(|p| p.and_then(|i| record.get(i)).unwrap_or_default());
+            writer.write_record(fields)?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cat_records() -> Result<()> {
+        let inputs = ["a.csv".to_string(), "-".to_string()];
+        let open = |input: &str| {
+            Ok(match input {
+                "a.csv" => "Name,Kit Number\nBuffon,77\n".as_bytes(),
+                _ => "Kit Number,Name,Club\n3,Chiellini,Juventus\n".as_bytes(),
+            })
+        };
+        let mut buf = Vec::new();
+        cat_records(&inputs, open, &mut buf, &CsvReaderConfig::default())?;
+        assert_eq!(
+            String::from_utf8(buf)?,
+            "Name,Kit Number,Club\nBuffon,77,\nChiellini,3,Juventus\n"
+        );
+
+        let inputs = ["-".to_string(), "-".to_string()];
+        let config = CsvReaderConfig::default();
+        assert!(cat_records(&inputs, open, &mut Vec::new(), &config).is_err());
+        Ok(())
+    }
+}
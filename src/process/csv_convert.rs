@@ -1,46 +1,492 @@
 use anyhow::Result;
-use csv::Reader;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::fs;
-
-use crate::OutputFormat;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Player {
-    #[serde(rename = "Name")]
-    name: String,
-    #[serde(rename = "Position")]
-    position: String,
-    #[serde(rename = "DOB")]
-    dob: String,
-    #[serde(rename = "Nationality")]
-    nationality: String,
-    #[serde(rename = "Kit Number")]
-    kit: u8,
-}
-
-pub fn process_csv(input: &str, output: String, output_format: OutputFormat) -> Result<()> {
-    let mut reader = Reader::from_path(input).unwrap();
-    // let players = reader
-    //     .deserialize()
-    //     .map(|record| record.unwrap())
-    //     .collect::<Vec<Player>>();
-
-    let mut ret = Vec::with_capacity(128);
-    let headers = reader.headers()?.clone();
-
-    for des_read in reader.records() {
-        let record = des_read?;
-        let json_record = headers.iter().zip(record.iter()).collect::<Value>();
-        ret.push(json_record);
-    }
-
-    let content = match output_format {
-        OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
-        OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
-    };
-    fs::write(output, content)?;
+use csv::{Reader, ReaderBuilder, StringRecord, StringRecordsIntoIter};
+use encoding_rs::Encoding;
+use serde_json::{Map, Value};
+use std::{
+    collections::HashSet,
+    io::{Read, Write},
+    vec,
+};
+
+use super::{
+    csv_output::write_records,
+    encoding::decode_reader,
+    flatten::unflatten,
+    tabular::{open_table, TableSource, TabularReader},
+};
+use crate::{ColumnType, Inference, OutputFormat, RowFilter};
+
+#[derive(Debug, Clone)]
+pub struct CsvReaderConfig {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub escape: Option<u8>,
+    pub comment: Option<u8>,
+    pub flexible: bool,
+    pub has_headers: bool,
+    // None 表示自动检测
+    pub encoding: Option<&'static Encoding>,
+}
+
+impl Default for CsvReaderConfig {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            escape: None,
+            comment: None,
+            flexible: false,
+            has_headers: true,
+            encoding: None,
+        }
+    }
+}
+
+impl CsvReaderConfig {
+    // 输入先转码为 UTF-8 再交给 csv 解析
+    pub fn reader<'a, R: Read + 'a>(&self, rdr: R) -> Result<Reader<Box<dyn Read + 'a>>> {
+        let rdr = decode_reader(rdr, self.encoding)?;
+        let reader = ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .escape(self.escape)
+            .double_quote(self.escape.is_none())
+            .comment(self.comment)
+            .flexible(self.flexible)
+            .has_headers(self.has_headers)
+            .from_reader(rdr);
+        Ok(reader)
+    }
+
+    // 无表头时使用第一行的列数生成 col_0..col_n
+    pub(crate) fn headers<R: Read>(&self, reader: &mut Reader<R>) -> Result<StringRecord> {
+        let headers = reader.headers()?;
+        if self.has_headers {
+            return Ok(headers.clone());
+        }
+        Ok((0..headers.len()).map(column_name).collect())
+    }
+}
+
+// 记录在写出前依次经过的转换
+#[derive(Debug, Clone, Default)]
+pub struct CsvTransform {
+    pub inference: Inference,
+    pub filter: Option<RowFilter>,
+    pub select: Vec<String>,
+    pub rename: Vec<(String, String)>,
+    pub unflatten: bool,
+}
+
+pub fn process_csv(
+    input: &str,
+    mut writer: impl Write,
+    output_format: OutputFormat,
+    source: &TableSource,
+    transform: &CsvTransform,
+) -> Result<()> {
+    let table = open_table(input, source, &transform.inference)?;
+    convert_table(table, &mut writer, output_format, transform)?;
+    writer.flush()?;
 
     Ok(())
 }
+
+// 逐条读取并写出, 内存占用与输入大小无关
+fn convert_table<W: Write>(
+    table: Box<dyn TabularReader + '_>,
+    writer: W,
+    output_format: OutputFormat,
+    transform: &CsvTransform,
+) -> Result<()> {
+    transform.check_columns(table.headers())?;
+    let records = table
+        .map(|record| transform.apply(record?))
+        .filter_map(Result::transpose);
+
+    write_records(records, writer, output_format)
+}
+
+// 采样行先缓存在内存中, 推断出列类型后再与剩余记录拼接
+pub struct CsvTable<'a> {
+    headers: StringRecord,
+    columns: Vec<String>,
+    types: Vec<ColumnType>,
+    strict: bool,
+    samples: vec::IntoIter<StringRecord>,
+    records: StringRecordsIntoIter<Box<dyn Read + 'a>>,
+}
+
+impl<'a> CsvTable<'a> {
+    pub fn new<R: Read + 'a>(
+        reader: R,
+        config: &CsvReaderConfig,
+        inference: &Inference,
+    ) -> Result<Self> {
+        let mut reader = config.reader(reader)?;
+        let headers = config.headers(&mut reader)?;
+        let mut records = reader.into_records();
+        let samples = records
+            .by_ref()
+            .take(inference.sample_rows())
+            .collect::<Result<Vec<_>, _>>()?;
+        let types = inference.column_types(&headers, &samples);
+        Ok(Self {
+            columns: headers.iter().map(String::from).collect(),
+            headers,
+            types,
+            strict: inference.is_strict(),
+            samples: samples.into_iter(),
+            records,
+        })
+    }
+}
+
+impl Iterator for CsvTable<'_> {
+    type Item = Result<Map<String, Value>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.samples.next() {
+            Some(record) => record,
+            None => match self.records.next()? {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e.into())),
+            },
+        };
+        Some(record_to_value(
+            &self.headers,
+            &self.types,
+            &record,
+            self.strict,
+        ))
+    }
+}
+
+impl TabularReader for CsvTable<'_> {
+    fn headers(&self) -> &[String] {
+        &self.columns
+    }
+}
+
+impl CsvTransform {
+    fn check_columns(&self, headers: &[String]) -> Result<()> {
+        let columns = self
+            .filter
+            .iter()
+            .flat_map(RowFilter::columns)
+            .chain(self.select.iter().map(String::as_str))
+            .chain(self.rename.iter().map(|(old, _)| old.as_str()));
+        for column in columns {
+            if !headers.iter().any(|h| h == column) {
+                anyhow::bail!("Column {:?} not found in headers", column);
+            }
+        }
+
+        // 重命名后的列名不能与保留的其他列或另一个新列名相同, 否则会覆盖数据
+        let kept = if self.select.is_empty() {
+            headers
+        } else {
+            self.select.as_slice()
+        };
+        let mut targets = HashSet::new();
+        for (_, new) in &self.rename {
+            let renamed = self.rename.iter().any(|(old, _)| old == new);
+            if !targets.insert(new) || (kept.contains(new) && !renamed) {
+                anyhow::bail!("Column {:?} already exists, pick another name", new);
+            }
+        }
+        Ok(())
+    }
+
+    // 依次执行 过滤 -> 选择列 -> 重命名 -> 展开嵌套, 被过滤掉的记录返回 None
+    fn apply(&self, mut record: Map<String, Value>) -> Result<Option<Value>> {
+        if let Some(filter) = &self.filter {
+            if !filter.matches(&record) {
+                return Ok(None);
+            }
+        }
+        if !self.select.is_empty() {
+            record = self
+                .select
+                .iter()
+                .filter_map(|column| record.remove_entry(column))
+                .collect();
+        }
+        if !self.rename.is_empty() {
+            record = record
+                .into_iter()
+                .map(
+                    |(k, v)| match self.rename.iter().find(|(old, _)| *old == k) {
+                        Some((_, new)) => (new.clone(), v),
+                        None => (k, v),
+                    },
+                )
+                .collect();
+        }
+        if self.unflatten {
+            return unflatten(record).map(Some);
+        }
+        Ok(Some(Value::Object(record)))
+    }
+}
+
+// flexible 模式下多出的字段同样以 col_n 命名
+pub(crate) fn record_to_value(
+    headers: &StringRecord,
+    types: &[ColumnType],
+    record: &StringRecord,
+    strict: bool,
+) -> Result<Map<String, Value>> {
+    let mut map = Map::with_capacity(record.len());
+    for (i, field) in record.iter().enumerate() {
+        let key = match headers.get(i) {
+            Some(header) => header.to_string(),
+            None => column_name(i),
+        };
+        let t = types.get(i).copied().unwrap_or(ColumnType::String);
+        let value = match t.parse(field) {
+            Some(value) => value,
+            None if strict => anyhow::bail!(
+                "Line {}, column {:?}: {:?} is not a valid {}",
+                record.position().map_or(0, |p| p.line()),
+                key,
+                field,
+                t
+            ),
+            None => Value::String(field.to_string()),
+        };
+        map.insert(key, value);
+    }
+    Ok(map)
+}
+
+pub(crate) fn column_indices(headers: &StringRecord, columns: &[String]) -> Result<Vec<usize>> {
+    columns
+        .iter()
+        .map(|column| match headers.iter().position(|h| h == column) {
+            Some(i) => Ok(i),
+            None => anyhow::bail!("Column {:?} not found in headers", column),
+        })
+        .collect()
+}
+
+pub(crate) fn column_name(i: usize) -> String {
+    format!("col_{}", i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "Name,Kit Number\nBuffon,77\nPerin,37\n";
+
+    fn convert(input: &str, format: OutputFormat, config: &CsvReaderConfig) -> Result<String> {
+        convert_with(input, format, config, &CsvTransform::default())
+    }
+
+    fn convert_with(
+        input: &str,
+        format: OutputFormat,
+        config: &CsvReaderConfig,
+        transform: &CsvTransform,
+    ) -> Result<String> {
+        let table = CsvTable::new(input.as_bytes(), config, &transform.inference)?;
+        let mut buf = Vec::new();
+        convert_table(Box::new(table), &mut buf, format, transform)?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    #[test]
+    fn test_convert_records_json() -> Result<()> {
+        let expected = serde_json::to_string_pretty(&serde_json::json!([
+            { "Name": "Buffon", "Kit Number": "77" },
+            { "Name": "Perin", "Kit Number": "37" },
+        ]))?;
+        let config = CsvReaderConfig::default();
+        assert_eq!(convert(INPUT, OutputFormat::Json, &config)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_records_yaml() -> Result<()> {
+        let config = CsvReaderConfig::default();
+        let ret: Vec<Value> = serde_yaml::from_str(&convert(INPUT, OutputFormat::Yaml, &config)?)?;
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[1]["Name"], "Perin");
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_records_headerless_tsv() -> Result<()> {
+        let config = CsvReaderConfig {
+            delimiter: b'\t',
+            comment: Some(b'#'),
+            flexible: true,
+            has_headers: false,
+            ..Default::default()
+        };
+        let input = "# players\nBuffon\t77\nPerin\t37\tItaly\n";
+        let ret: Vec<Value> = serde_json::from_str(&convert(input, OutputFormat::Json, &config)?)?;
+        assert_eq!(ret.len(), 2);
+        assert_eq!(
+            ret[0],
+            serde_json::json!({ "col_0": "Buffon", "col_1": "77" })
+        );
+        assert_eq!(ret[1]["col_2"], "Italy");
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_records_csv_rejects_new_fields() -> Result<()> {
+        let config = CsvReaderConfig {
+            flexible: true,
+            ..Default::default()
+        };
+        let input = "a,b\n1,2\n3,4,5\n";
+        let ret = convert(input, OutputFormat::Csv, &config);
+        assert!(ret.unwrap_err().to_string().contains("\"col_2\""));
+
+        // 后续记录缺少的字段写为空值
+        let input = "a,b,c\n1,2,3\n4,5\n";
+        assert_eq!(
+            convert(input, OutputFormat::Csv, &config)?,
+            "a,b,c\n1,2,3\n4,5,\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_records_infer_types() -> Result<()> {
+        let config = CsvReaderConfig::default();
+        let input = "Name,Kit Number,Rating\nBuffon,77,\nPerin,37,8.5\nPinsoglio,31x,7\n";
+        let transform = CsvTransform {
+            inference: Inference::Sample(2),
+            ..Default::default()
+        };
+        let ret = convert_with(input, OutputFormat::Json, &config, &transform)?;
+        let ret: Vec<Value> = serde_json::from_str(&ret)?;
+        assert_eq!(ret[0]["Kit Number"], 77);
+        assert_eq!(ret[0]["Rating"], Value::Null);
+        assert_eq!(ret[1]["Rating"], 8.5);
+        // 采样之外无法解析的值保留为字符串
+        assert_eq!(ret[2]["Kit Number"], "31x");
+
+        let schema = [("Kit Number".to_string(), ColumnType::Integer)].into();
+        let transform = CsvTransform {
+            inference: Inference::Schema(schema),
+            ..Default::default()
+        };
+        assert!(convert_with(input, OutputFormat::Json, &config, &transform).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_juventus_to_all_formats() -> Result<()> {
+        let input = std::fs::read_to_string("assets/juventus.csv")?;
+        let config = CsvReaderConfig::default();
+        let transform = CsvTransform {
+            inference: Inference::Sample(100),
+            ..Default::default()
+        };
+
+        let ndjson = convert_with(&input, OutputFormat::Ndjson, &config, &transform)?;
+        let first: Value = serde_json::from_str(ndjson.lines().next().unwrap())?;
+        assert_eq!(first["Name"], "Wojciech Szczesny");
+        assert_eq!(first["Kit Number"], 1);
+        assert_eq!(ndjson.lines().count(), 27);
+
+        let toml = convert_with(&input, OutputFormat::Toml, &config, &transform)?;
+        let table: toml::Table = toml::from_str(&toml)?;
+        assert_eq!(table["records"].as_array().unwrap().len(), 27);
+
+        let xml = convert_with(&input, OutputFormat::Xml, &config, &transform)?;
+        assert!(xml.contains("<Kit_Number>1</Kit_Number>"));
+        assert_eq!(xml.matches("<record>").count(), 27);
+
+        let csv = convert_with(&input, OutputFormat::Csv, &config, &transform)?;
+        assert_eq!(csv, input);
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_records_unflatten() -> Result<()> {
+        let config = CsvReaderConfig::default();
+        let transform = CsvTransform {
+            inference: Inference::Sample(100),
+            unflatten: true,
+            ..Default::default()
+        };
+        let input = "name,address.city,tags[0],tags[1]\nBuffon,Turin,gk,77\n";
+        let ret = convert_with(input, OutputFormat::Json, &config, &transform)?;
+        let ret: Vec<Value> = serde_json::from_str(&ret)?;
+        let expected = serde_json::json!({
+            "name": "Buffon",
+            "address": { "city": "Turin" },
+            "tags": ["gk", 77],
+        });
+        assert_eq!(ret[0], expected);
+
+        let ret = convert_with(input, OutputFormat::Csv, &config, &transform)?;
+        assert_eq!(ret, input);
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_records_select_rename_where() -> Result<()> {
+        let input = std::fs::read_to_string("assets/juventus.csv")?;
+        let config = CsvReaderConfig::default();
+        let transform = CsvTransform {
+            filter: Some("Position == 'Goalkeeper' && `Kit Number` < 40".parse()?),
+            select: vec!["Kit Number".into(), "Name".into()],
+            rename: vec![("Kit Number".into(), "kit".into())],
+            ..Default::default()
+        };
+        let ret = convert_with(&input, OutputFormat::Csv, &config, &transform)?;
+        let expected = "\
+kit,Name
+1,Wojciech Szczesny
+37,Mattia Perin
+31,Carlo Pinsoglio
+";
+        assert_eq!(ret, expected);
+
+        for rename in [
+            vec![("Name".into(), "Position".into())],
+            vec![("Name".into(), "n".into()), ("DOB".into(), "n".into())],
+        ] {
+            let transform = CsvTransform {
+                rename,
+                ..Default::default()
+            };
+            assert!(convert_with(&input, OutputFormat::Csv, &config, &transform).is_err());
+        }
+        // 交换列名, 或新列名对应的列没有被选中时不冲突
+        let transform = CsvTransform {
+            select: vec!["Name".into(), "DOB".into()],
+            rename: vec![("Name".into(), "DOB".into()), ("DOB".into(), "Name".into())],
+            ..Default::default()
+        };
+        let ret = convert_with(&input, OutputFormat::Csv, &config, &transform)?;
+        assert!(ret.starts_with("DOB,Name\n"));
+        let transform = CsvTransform {
+            select: vec!["Name".into()],
+            rename: vec![("Name".into(), "Position".into())],
+            ..Default::default()
+        };
+        assert!(convert_with(&input, OutputFormat::Csv, &config, &transform).is_ok());
+
+        let transform = CsvTransform {
+            select: vec!["Club".into()],
+            ..Default::default()
+        };
+        assert!(convert_with(&input, OutputFormat::Csv, &config, &transform).is_err());
+
+        let transform = CsvTransform {
+            filter: Some("Position == 'Goalkeeper' || Club == 'Juventus'".parse()?),
+            ..Default::default()
+        };
+        let ret = convert_with(&input, OutputFormat::Csv, &config, &transform);
+        assert!(ret.unwrap_err().to_string().contains("\"Club\""));
+        Ok(())
+    }
+}
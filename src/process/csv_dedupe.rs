@@ -0,0 +1,94 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    io::{Read, Write},
+};
+
+use super::{
+    csv_convert::{column_indices, record_to_value},
+    csv_output::write_records,
+};
+use crate::{read_data, CsvReaderConfig, OutputFormat};
+
+pub fn process_csv_dedupe(
+    input: &str,
+    mut writer: impl Write,
+    on: &[String],
+    config: &CsvReaderConfig,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let reader = read_data(input)?;
+    dedupe_records(reader, &mut writer, on, config, output_format)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+// 保留每个 key 第一次出现的行; on 为空时按整行去重
+fn dedupe_records<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    on: &[String],
+    config: &CsvReaderConfig,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let mut reader = config.reader(reader)?;
+    let headers = config.headers(&mut reader)?;
+    let columns = column_indices(&headers, on)?;
+
+    let mut seen = HashSet::new();
+    let records = reader
+        .into_records()
+        .filter_map(|record| {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => return Some(Err(anyhow::Error::from(e))),
+            };
+            let key = if columns.is_empty() {
+                record.iter().map(String::from).collect::<Vec<_>>()
+            } else {
+                columns
+                    .iter()
+                    .map(|&i| record.get(i).unwrap_or_default().to_string())
+                    .collect()
+            };
+            seen.insert(key).then_some(Ok(record))
+        })
+        .map(|record| record_to_value(&headers, &[], &record?, false).map(Value::Object));
+
+    write_records(records, writer, output_format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dedupe(input: &str, on: &[&str]) -> Result<String> {
+        let on = on.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let mut buf = Vec::new();
+        dedupe_records(
+            input.as_bytes(),
+            &mut buf,
+            &on,
+            &CsvReaderConfig::default(),
+            OutputFormat::Csv,
+        )?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    #[test]
+    fn test_dedupe_records() -> Result<()> {
+        let input = "Name,Club\nBuffon,Juventus\nBuffon,PSG\nBuffon,Juventus\nPerin,Juventus\n";
+        assert_eq!(
+            dedupe(input, &[])?,
+            "Name,Club\nBuffon,Juventus\nBuffon,PSG\nPerin,Juventus\n"
+        );
+        assert_eq!(
+            dedupe(input, &["Name"])?,
+            "Name,Club\nBuffon,Juventus\nPerin,Juventus\n"
+        );
+        assert!(dedupe(input, &["Kit"]).is_err());
+        Ok(())
+    }
+}
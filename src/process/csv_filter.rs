@@ -0,0 +1,304 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::{cmp::Ordering, iter::Peekable, str::FromStr, vec::IntoIter};
+
+use super::csv_output::scalar_to_string;
+
+// 形如 `Position == 'Goalkeeper' && `Kit Number` < 10` 的行过滤表达式
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowFilter {
+    And(Box<RowFilter>, Box<RowFilter>),
+    Or(Box<RowFilter>, Box<RowFilter>),
+    Compare {
+        column: String,
+        op: CompareOp,
+        value: Value,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Op(CompareOp),
+    Ident(String),
+    Literal(Value),
+}
+
+impl RowFilter {
+    pub fn matches(&self, record: &Map<String, Value>) -> bool {
+        match self {
+            RowFilter::And(a, b) => a.matches(record) && b.matches(record),
+            RowFilter::Or(a, b) => a.matches(record) || b.matches(record),
+            RowFilter::Compare { column, op, value } => {
+                let field = record.get(column).unwrap_or(&Value::Null);
+                compare(field, *op, value)
+            }
+        }
+    }
+
+    // 表达式中引用的所有列名, 按出现顺序
+    pub fn columns(&self) -> Vec<&str> {
+        match self {
+            RowFilter::And(a, b) | RowFilter::Or(a, b) => {
+                let mut ret = a.columns();
+                ret.extend(b.columns());
+                ret
+            }
+            RowFilter::Compare { column, .. } => vec![column.as_str()],
+        }
+    }
+
+    fn parse_or(tokens: &mut Peekable<IntoIter<Token>>) -> Result<Self> {
+        let mut left = Self::parse_and(tokens)?;
+        while tokens.next_if_eq(&Token::Or).is_some() {
+            let right = Self::parse_and(tokens)?;
+            left = RowFilter::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(tokens: &mut Peekable<IntoIter<Token>>) -> Result<Self> {
+        let mut left = Self::parse_compare(tokens)?;
+        while tokens.next_if_eq(&Token::And).is_some() {
+            let right = Self::parse_compare(tokens)?;
+            left = RowFilter::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_compare(tokens: &mut Peekable<IntoIter<Token>>) -> Result<Self> {
+        match tokens.next() {
+            Some(Token::LParen) => {
+                let expr = Self::parse_or(tokens)?;
+                match tokens.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    t => anyhow::bail!("Expect ')', got {:?}", t),
+                }
+            }
+            Some(Token::Ident(column)) => {
+                let op = match tokens.next() {
+                    Some(Token::Op(op)) => op,
+                    t => anyhow::bail!(
+                        "Expect a comparison operator after {:?}, got {:?}",
+                        column,
+                        t
+                    ),
+                };
+                let value = match tokens.next() {
+                    Some(Token::Literal(value)) => value,
+                    t => anyhow::bail!("Expect a literal after {:?}, got {:?}", column, t),
+                };
+                Ok(RowFilter::Compare { column, op, value })
+            }
+            t => anyhow::bail!("Expect a column name or '(', got {:?}", t),
+        }
+    }
+}
+
+impl FromStr for RowFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        let mut tokens = tokenize(expr)?.into_iter().peekable();
+        let filter = Self::parse_or(&mut tokens)?;
+        if let Some(t) = tokens.next() {
+            anyhow::bail!("Unexpected {:?} in filter {:?}", t, expr);
+        }
+        Ok(filter)
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' {
+                    Token::LParen
+                } else {
+                    Token::RParen
+                });
+            }
+            '&' | '|' => {
+                chars.next();
+                if chars.next() != Some(c) {
+                    anyhow::bail!("Expect '{}{}' in filter {:?}", c, c, expr);
+                }
+                tokens.push(if c == '&' { Token::And } else { Token::Or });
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                let op = match (c, eq) {
+                    ('=', true) => CompareOp::Eq,
+                    ('!', true) => CompareOp::Ne,
+                    ('<', false) => CompareOp::Lt,
+                    ('<', true) => CompareOp::Le,
+                    ('>', false) => CompareOp::Gt,
+                    ('>', true) => CompareOp::Ge,
+                    _ => anyhow::bail!("Invalid operator in filter {:?}", expr),
+                };
+                tokens.push(Token::Op(op));
+            }
+            // 反引号包裹含空格的列名, 引号包裹字符串字面量
+            '`' | '\'' | '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == c => break,
+                        Some(ch) => s.push(ch),
+                        None => anyhow::bail!("Unclosed {} in filter {:?}", c, expr),
+                    }
+                }
+                tokens.push(if c == '`' {
+                    Token::Ident(s)
+                } else {
+                    Token::Literal(Value::String(s))
+                });
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(ch) =
+                    chars.next_if(|ch| !ch.is_whitespace() && !"()&|=!<>`'\"".contains(*ch))
+                {
+                    word.push(ch);
+                }
+                tokens.push(word_token(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn word_token(word: String) -> Token {
+    match word.to_lowercase().as_str() {
+        "and" => Token::And,
+        "or" => Token::Or,
+        "null" => Token::Literal(Value::Null),
+        "true" => Token::Literal(Value::Bool(true)),
+        "false" => Token::Literal(Value::Bool(false)),
+        _ => match serde_json::from_str::<serde_json::Number>(&word) {
+            Ok(n) => Token::Literal(Value::Number(n)),
+            Err(_) => Token::Ident(word),
+        },
+    }
+}
+
+fn compare(field: &Value, op: CompareOp, literal: &Value) -> bool {
+    let ordering = match literal {
+        Value::Null => {
+            let is_null = field.is_null() || field.as_str() == Some("");
+            return match op {
+                CompareOp::Eq => is_null,
+                CompareOp::Ne => !is_null,
+                _ => false,
+            };
+        }
+        Value::Number(n) => {
+            as_f64(field).and_then(|f| f.partial_cmp(&n.as_f64().unwrap_or(f64::NAN)))
+        }
+        Value::Bool(b) => as_bool(field).map(|f| f.cmp(b)),
+        literal => Some(
+            scalar_to_string(field)
+                .as_str()
+                .cmp(&scalar_to_string(literal)),
+        ),
+    };
+
+    match ordering {
+        Some(ordering) => match op {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        },
+        // 类型不兼容时只有 != 成立
+        None => op == CompareOp::Ne,
+    }
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => s.to_lowercase().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_row_filter_parse() -> Result<()> {
+        let filter: RowFilter = "Position == 'Goalkeeper' && `Kit Number` < 10".parse()?;
+        let expected = RowFilter::And(
+            Box::new(RowFilter::Compare {
+                column: "Position".into(),
+                op: CompareOp::Eq,
+                value: json!("Goalkeeper"),
+            }),
+            Box::new(RowFilter::Compare {
+                column: "Kit Number".into(),
+                op: CompareOp::Lt,
+                value: json!(10),
+            }),
+        );
+        assert_eq!(filter, expected);
+        assert!("Position ==".parse::<RowFilter>().is_err());
+        assert!("(a == 1".parse::<RowFilter>().is_err());
+        let filter: RowFilter = "a == 1 || (b < 2 && `c d` != 'x')".parse()?;
+        assert_eq!(filter.columns(), ["a", "b", "c d"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_filter_matches() -> Result<()> {
+        let buffon = record(json!({ "Position": "Goalkeeper", "Kit Number": "77", "Club": "" }));
+        let filter: RowFilter =
+            "Position == 'Goalkeeper' and (`Kit Number` < 10 or `Kit Number` >= 77)".parse()?;
+        assert!(filter.matches(&buffon));
+        let filter: RowFilter = "Club == null || Position != \"Goalkeeper\"".parse()?;
+        assert!(filter.matches(&buffon));
+        let filter: RowFilter = "Position > 1".parse()?;
+        assert!(!filter.matches(&buffon));
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use super::{csv_convert::column_indices, csv_output::write_records};
+use crate::{read_data, CsvReaderConfig, OutputFormat};
+
+// `--agg sum(x),count()`, 输出列名与参数写法一致
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Aggregate {
+    // count() 统计行数, count(x) 统计 x 非空的行数
+    Count(Option<String>),
+    Sum(String),
+    Mean(String),
+    Min(String),
+    Max(String),
+}
+
+#[derive(Debug, Default, Clone)]
+struct Accumulator {
+    count: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+pub fn process_csv_group(
+    input: &str,
+    mut writer: impl Write,
+    by: &[String],
+    aggregates: &[Aggregate],
+    config: &CsvReaderConfig,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let reader = read_data(input)?;
+    group_records(reader, &mut writer, by, aggregates, config, output_format)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+// 分组按第一次出现的顺序输出, 内存占用与分组数成正比
+fn group_records<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    by: &[String],
+    aggregates: &[Aggregate],
+    config: &CsvReaderConfig,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let mut reader = config.reader(reader)?;
+    let headers = config.headers(&mut reader)?;
+    let group_columns = column_indices(&headers, by)?;
+    let agg_columns = aggregates
+        .iter()
+        .map(|agg| match agg.column() {
+            Some(column) => Ok(Some(column_indices(&headers, &[column.to_string()])?[0])),
+            None => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut groups: Vec<(Vec<String>, Vec<Accumulator>)> = Vec::new();
+    let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+    for record in reader.into_records() {
+        let record = record?;
+        let key = group_columns
+            .iter()
+            .map(|&i| record.get(i).unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        let i = *index.entry(key.clone()).or_insert_with(|| {
+            groups.push((key, vec![Accumulator::default(); aggregates.len()]));
+            groups.len() - 1
+        });
+
+        let accs = groups[i].1.iter_mut().zip(aggregates).zip(&agg_columns);
+        for ((acc, agg), column) in accs {
+            let Some(c) = *column else {
+                acc.count += 1;
+                continue;
+            };
+            let field = record.get(c).unwrap_or_default().trim();
+            if field.is_empty() {
+                continue;
+            }
+            acc.count += 1;
+            match field.parse::<f64>() {
+                Ok(n) => acc.update(n),
+                // count(x) 不要求是数字
+                Err(_) if matches!(agg, Aggregate::Count(_)) => {}
+                Err(_) => anyhow::bail!(
+                    "Line {}, column {:?}: {:?} is not a number",
+                    record.position().map_or(0, |p| p.line()),
+                    &headers[c],
+                    field
+                ),
+            }
+        }
+    }
+
+    let records = groups.into_iter().map(|(key, accs)| {
+        let mut map = by
+            .iter()
+            .cloned()
+            .zip(key.into_iter().map(Value::String))
+            .collect::<Map<_, _>>();
+        for (agg, acc) in aggregates.iter().zip(accs) {
+            map.insert(agg.to_string(), acc.finish(agg));
+        }
+        Ok(Value::Object(map))
+    });
+    write_records(records, writer, output_format)
+}
+
+impl Accumulator {
+    fn update(&mut self, n: f64) {
+        self.sum += n;
+        self.min = Some(self.min.map_or(n, |m| m.min(n)));
+        self.max = Some(self.max.map_or(n, |m| m.max(n)));
+    }
+
+    fn finish(self, agg: &Aggregate) -> Value {
+        match agg {
+            Aggregate::Count(_) => Value::from(self.count),
+            Aggregate::Sum(_) => number(self.sum),
+            Aggregate::Mean(_) if self.count == 0 => Value::Null,
+            Aggregate::Mean(_) => number(self.sum / self.count as f64),
+            Aggregate::Min(_) => self.min.map_or(Value::Null, number),
+            Aggregate::Max(_) => self.max.map_or(Value::Null, number),
+        }
+    }
+}
+
+// 整数结果不带小数点输出
+pub(crate) fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+impl Aggregate {
+    fn column(&self) -> Option<&str> {
+        match self {
+            Aggregate::Count(column) => column.as_deref(),
+            Aggregate::Sum(c) | Aggregate::Mean(c) | Aggregate::Min(c) | Aggregate::Max(c) => {
+                Some(c)
+            }
+        }
+    }
+}
+
+impl FromStr for Aggregate {
+    type Err = anyhow::Error;
+
+    fn from_str(agg: &str) -> Result<Self, Self::Err> {
+        let parsed = agg.trim().strip_suffix(')').and_then(|s| s.split_once('('));
+        let (func, column) = match parsed {
+            Some((func, column)) => (func.trim().to_lowercase(), column.trim().to_string()),
+            None => anyhow::bail!("Expect an aggregate like sum(x) or count(), got {:?}", agg),
+        };
+        match (func.as_str(), column.is_empty()) {
+            ("count", true) => Ok(Aggregate::Count(None)),
+            ("count", false) => Ok(Aggregate::Count(Some(column))),
+            (_, true) => anyhow::bail!("{}() requires a column", func),
+            ("sum", false) => Ok(Aggregate::Sum(column)),
+            ("mean" | "avg", false) => Ok(Aggregate::Mean(column)),
+            ("min", false) => Ok(Aggregate::Min(column)),
+            ("max", false) => Ok(Aggregate::Max(column)),
+            (v, _) => anyhow::bail!("Unsupported aggregate function {:?}", v),
+        }
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let func = match self {
+            Aggregate::Count(_) => "count",
+            Aggregate::Sum(_) => "sum",
+            Aggregate::Mean(_) => "mean",
+            Aggregate::Min(_) => "min",
+            Aggregate::Max(_) => "max",
+        };
+        write!(fmt, "{}({})", func, self.column().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str =
+        "Position,Name,Kit Number\nGoalkeeper,Buffon,77\nDefender,Chiellini,3\nGoalkeeper,Perin,\nDefender,Bonucci,19\n";
+
+    fn group(by: &str, aggs: &str) -> Result<String> {
+        let aggs = aggs
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<Aggregate>>>()?;
+        let mut buf = Vec::new();
+        group_records(
+            INPUT.as_bytes(),
+            &mut buf,
+            &[by.to_string()],
+            &aggs,
+            &CsvReaderConfig::default(),
+            OutputFormat::Csv,
+        )?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    #[test]
+    fn test_aggregate_parse() -> Result<()> {
+        assert_eq!("count()".parse::<Aggregate>()?, Aggregate::Count(None));
+        assert_eq!(
+            " SUM(Kit Number) ".parse::<Aggregate>()?,
+            Aggregate::Sum("Kit Number".into())
+        );
+        assert_eq!("avg(x)".parse::<Aggregate>()?.to_string(), "mean(x)");
+        assert!("sum()".parse::<Aggregate>().is_err());
+        assert!("median(x)".parse::<Aggregate>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_records() -> Result<()> {
+        assert_eq!(
+            group(
+                "Position",
+                "count(),count(Kit Number),sum(Kit Number),mean(Kit Number)"
+            )?,
+            "Position,count(),count(Kit Number),sum(Kit Number),mean(Kit Number)\n\
+             Goalkeeper,2,1,77,77\n\
+             Defender,2,2,22,11\n"
+        );
+        assert!(group("Position", "sum(Name)").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate};
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+use std::{collections::HashMap, fmt, fs, path::Path, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    Null,
+    Boolean,
+    Integer,
+    Float,
+    Date,
+    String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum Inference {
+    #[default]
+    None,
+    // 读取前 n 行推断每列类型, 之后无法解析的值退化为字符串
+    Sample(usize),
+    // 用户指定的列类型, 无法解析时报错
+    Schema(HashMap<String, ColumnType>),
+}
+
+impl ColumnType {
+    pub fn infer(field: &str) -> Self {
+        if is_null(field) {
+            ColumnType::Null
+        } else if parse_bool(field).is_some() {
+            ColumnType::Boolean
+        } else if parse_int(field).is_some() {
+            ColumnType::Integer
+        } else if parse_float(field).is_some() {
+            ColumnType::Float
+        } else if is_date(field) {
+            ColumnType::Date
+        } else {
+            ColumnType::String
+        }
+    }
+
+    // 取能同时容纳两种类型的最窄类型
+    pub fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnType::Null, t) | (t, ColumnType::Null) => t,
+            (ColumnType::Integer, ColumnType::Float) | (ColumnType::Float, ColumnType::Integer) => {
+                ColumnType::Float
+            }
+            _ => ColumnType::String,
+        }
+    }
+
+    pub fn parse(&self, field: &str) -> Option<Value> {
+        if *self != ColumnType::String && is_null(field) {
+            return Some(Value::Null);
+        }
+        match self {
+            ColumnType::Null => None,
+            ColumnType::Boolean => parse_bool(field).map(Value::Bool),
+            ColumnType::Integer => parse_int(field).map(Value::from),
+            ColumnType::Float => parse_float(field).map(Value::Number),
+            ColumnType::Date => is_date(field).then(|| Value::String(field.to_string())),
+            ColumnType::String => Some(Value::String(field.to_string())),
+        }
+    }
+}
+
+impl Inference {
+    pub fn load_schema(path: impl AsRef<Path>) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let schema = serde_yaml::from_str(&content)?;
+        Ok(Inference::Schema(schema))
+    }
+
+    pub fn sample_rows(&self) -> usize {
+        match self {
+            Inference::Sample(n) => *n,
+            _ => 0,
+        }
+    }
+
+    pub fn column_types(
+        &self,
+        headers: &StringRecord,
+        samples: &[StringRecord],
+    ) -> Vec<ColumnType> {
+        match self {
+            Inference::None => vec![ColumnType::String; headers.len()],
+            Inference::Sample(_) => {
+                let mut types = vec![ColumnType::Null; headers.len()];
+                for record in samples {
+                    for (t, field) in types.iter_mut().zip(record.iter()) {
+                        *t = t.merge(ColumnType::infer(field));
+                    }
+                }
+                types
+            }
+            Inference::Schema(schema) => headers
+                .iter()
+                .map(|h| schema.get(h).copied().unwrap_or(ColumnType::String))
+                .collect(),
+        }
+    }
+
+    pub fn is_strict(&self) -> bool {
+        matches!(self, Inference::Schema(_))
+    }
+}
+
+impl From<ColumnType> for &'static str {
+    fn from(t: ColumnType) -> Self {
+        match t {
+            ColumnType::Null => "null",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Date => "date",
+            ColumnType::String => "string",
+        }
+    }
+}
+
+impl FromStr for ColumnType {
+    type Err = anyhow::Error;
+
+    fn from_str(t: &str) -> Result<Self, Self::Err> {
+        match t.to_lowercase().as_str() {
+            "null" => Ok(ColumnType::Null),
+            "boolean" | "bool" => Ok(ColumnType::Boolean),
+            "integer" | "int" => Ok(ColumnType::Integer),
+            "float" | "number" => Ok(ColumnType::Float),
+            "date" => Ok(ColumnType::Date),
+            "string" => Ok(ColumnType::String),
+            v => anyhow::bail!("Unsupported column type {:?}", v),
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", Into::<&str>::into(*self))
+    }
+}
+
+fn is_null(field: &str) -> bool {
+    field.is_empty() || field.eq_ignore_ascii_case("null")
+}
+
+fn parse_bool(field: &str) -> Option<bool> {
+    if field.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if field.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn parse_int(field: &str) -> Option<i64> {
+    if is_code(field) {
+        return None;
+    }
+    field.parse().ok()
+}
+
+// NaN/inf 无法表示为 JSON 数字
+fn parse_float(field: &str) -> Option<Number> {
+    if is_code(field) {
+        return None;
+    }
+    field.parse::<f64>().ok().and_then(Number::from_f64)
+}
+
+// 邮编、电话、编号等带前导 0 或 + 的值按数字解析会丢失字符, 保留为字符串
+fn is_code(field: &str) -> bool {
+    let digits = field.strip_prefix('-').unwrap_or(field);
+    field.starts_with('+')
+        || (digits.starts_with('0') && digits[1..].starts_with(|c: char| c.is_ascii_digit()))
+}
+
+fn is_date(field: &str) -> bool {
+    NaiveDate::parse_from_str(field, "%Y-%m-%d").is_ok()
+        || DateTime::parse_from_rfc3339(field).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_type_infer() {
+        assert_eq!(ColumnType::infer(""), ColumnType::Null);
+        assert_eq!(ColumnType::infer("TRUE"), ColumnType::Boolean);
+        assert_eq!(ColumnType::infer("77"), ColumnType::Integer);
+        assert_eq!(ColumnType::infer("-1.5"), ColumnType::Float);
+        assert_eq!(ColumnType::infer("1990-04-18"), ColumnType::Date);
+        assert_eq!(ColumnType::infer("Apr 18, 1990 (29)"), ColumnType::String);
+        assert_eq!(ColumnType::infer("NaN"), ColumnType::String);
+        assert_eq!(ColumnType::infer("0"), ColumnType::Integer);
+        assert_eq!(ColumnType::infer("-0.5"), ColumnType::Float);
+        assert_eq!(ColumnType::infer("00123"), ColumnType::String);
+        assert_eq!(ColumnType::infer("-007"), ColumnType::String);
+        assert_eq!(ColumnType::infer("01.5"), ColumnType::String);
+        assert_eq!(ColumnType::infer("+39011"), ColumnType::String);
+        assert_eq!(ColumnType::Integer.parse("00123"), None);
+    }
+
+    #[test]
+    fn test_column_type_merge() {
+        let t = [ColumnType::Null, ColumnType::Integer, ColumnType::Float]
+            .into_iter()
+            .fold(ColumnType::Null, ColumnType::merge);
+        assert_eq!(t, ColumnType::Float);
+        assert_eq!(
+            ColumnType::Integer.merge(ColumnType::Boolean),
+            ColumnType::String
+        );
+    }
+
+    #[test]
+    fn test_column_type_parse() {
+        assert_eq!(ColumnType::Integer.parse("10"), Some(Value::from(10)));
+        assert_eq!(ColumnType::Integer.parse(""), Some(Value::Null));
+        assert_eq!(ColumnType::Integer.parse("ten"), None);
+        assert_eq!(ColumnType::String.parse(""), Some(Value::from("")));
+    }
+}
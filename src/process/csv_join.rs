@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use csv::StringRecord;
+use serde_json::{Map, Value};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use super::{csv_convert::column_indices, csv_output::write_records};
+use crate::{read_data, CsvReaderConfig, JoinMode, OutputFormat};
+
+pub fn process_csv_join(
+    left: &str,
+    right: &str,
+    mut writer: impl Write,
+    on: &[String],
+    mode: JoinMode,
+    config: &CsvReaderConfig,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let left = read_data(left)?;
+    let right = read_data(right)?;
+    join_records(left, right, &mut writer, on, mode, config, output_format)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+// 右表完整读入并按 key 建立索引, 左表逐行流式处理
+fn join_records<L: Read, R: Read, W: Write>(
+    left: L,
+    right: R,
+    writer: W,
+    on: &[String],
+    mode: JoinMode,
+    config: &CsvReaderConfig,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let mut right_reader = config.reader(right)?;
+    let right_headers = config.headers(&mut right_reader)?;
+    let right_keys = column_indices(&right_headers, on).context("Invalid right csv")?;
+    let right_rows = right_reader.into_records().collect::<Result<Vec<_>, _>>()?;
+    let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    for (i, row) in right_rows.iter().enumerate() {
+        index.entry(join_key(row, &right_keys)).or_default().push(i);
+    }
+
+    let mut left_reader = config.reader(left)?;
+    let left_headers = config.headers(&mut left_reader)?;
+    let left_keys = column_indices(&left_headers, on).context("Invalid left csv")?;
+
+    // key 列只保留左表的一份, 右表其余与已有列同名的列加 _right 后缀, 直到不再重名
+    let right_columns = (0..right_headers.len())
+        .filter(|i| !right_keys.contains(i))
+        .collect::<Vec<_>>();
+    let mut headers = left_headers.iter().map(String::from).collect::<Vec<_>>();
+    for &i in &right_columns {
+        let mut name = right_headers[i].to_string();
+        while headers.contains(&name) {
+            name.push_str("_right");
+        }
+        headers.push(name);
+    }
+
+    let field = |record: Option<&StringRecord>, i: usize| {
+        record
+            .and_then(|r| r.get(i))
+            .map_or(Value::Null, |f| Value::String(f.to_string()))
+    };
+    let joined_row = |left: Option<&StringRecord>, right: Option<&StringRecord>| {
+        let mut values = (0..left_headers.len())
+            .map(|i| field(left, i))
+            .collect::<Vec<_>>();
+        // 仅出现在右表的行, key 取自右表
+        if left.is_none() {
+            for (&li, &ri) in left_keys.iter().zip(&right_keys) {
+                values[li] = field(right, ri);
+            }
+        }
+        values.extend(right_columns.iter().map(|&i| field(right, i)));
+        Value::Object(headers.iter().cloned().zip(values).collect::<Map<_, _>>())
+    };
+
+    let matched = vec![Cell::new(false); right_rows.len()];
+    let records = left_reader
+        .into_records()
+        .flat_map(|record| {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => return vec![Err(e.into())],
+            };
+            match index.get(&join_key(&record, &left_keys)) {
+                Some(rows) => rows
+                    .iter()
+                    .map(|&i| {
+                        matched[i].set(true);
+                        Ok(joined_row(Some(&record), Some(&right_rows[i])))
+                    })
+                    .collect(),
+                None if mode == JoinMode::Inner => Vec::new(),
+                None => vec![Ok(joined_row(Some(&record), None))],
+            }
+        })
+        // 左表处理完后才会求值, 此时 matched 已完整
+        .chain(
+            (0..right_rows.len())
+                .filter(|&i| mode == JoinMode::Outer && !matched[i].get())
+                .map(|i| Ok(joined_row(None, Some(&right_rows[i])))),
+        );
+
+    write_records(records, writer, output_format)
+}
+
+fn join_key(record: &StringRecord, keys: &[usize]) -> Vec<String> {
+    keys.iter()
+        .map(|&i| record.get(i).unwrap_or_default().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: &str = "id,Name\n1,Buffon\n2,Chiellini\n3,Dybala\n";
+    const RIGHT: &str =
+        "id,Name,Kit Number\n1,Gianluigi Buffon,77\n3,Paulo Dybala,10\n4,Pjanic,5\n";
+
+    fn join(mode: JoinMode) -> Result<String> {
+        let mut buf = Vec::new();
+        join_records(
+            LEFT.as_bytes(),
+            RIGHT.as_bytes(),
+            &mut buf,
+            &["id".to_string()],
+            mode,
+            &CsvReaderConfig::default(),
+            OutputFormat::Csv,
+        )?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    #[test]
+    fn test_join_records_modes() -> Result<()> {
+        let header = "id,Name,Name_right,Kit Number\n";
+        let both = "1,Buffon,Gianluigi Buffon,77\n3,Dybala,Paulo Dybala,10\n";
+        assert_eq!(join(JoinMode::Inner)?, format!("{}{}", header, both));
+        assert_eq!(
+            join(JoinMode::Left)?,
+            format!(
+                "{}1,Buffon,Gianluigi Buffon,77\n2,Chiellini,,\n3,Dybala,Paulo Dybala,10\n",
+                header
+            )
+        );
+        assert!(join(JoinMode::Outer)?.ends_with("3,Dybala,Paulo Dybala,10\n4,,Pjanic,5\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_records_unique_headers() -> Result<()> {
+        let left = "id,Name,Name_right\n1,Buffon,GB\n";
+        let mut buf = Vec::new();
+        join_records(
+            left.as_bytes(),
+            RIGHT.as_bytes(),
+            &mut buf,
+            &["id".to_string()],
+            JoinMode::Inner,
+            &CsvReaderConfig::default(),
+            OutputFormat::Json,
+        )?;
+        let ret: Vec<Value> = serde_json::from_slice(&buf)?;
+        assert_eq!(ret[0]["Name_right"], "GB");
+        assert_eq!(ret[0]["Name_right_right"], "Gianluigi Buffon");
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_records_missing_key() {
+        let mut buf = Vec::new();
+        let ret = join_records(
+            LEFT.as_bytes(),
+            RIGHT.as_bytes(),
+            &mut buf,
+            &["Club".to_string()],
+            JoinMode::Inner,
+            &CsvReaderConfig::default(),
+            OutputFormat::Json,
+        );
+        assert!(ret.is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::Result;
+use csv::Writer;
+use serde::{ser::SerializeSeq, Serializer};
+use serde_json::{Map, Value};
+use std::io::Write;
+
+use super::flatten::flatten;
+use crate::OutputFormat;
+
+// 所有格式均逐条写出, 不在内存中保留已写出的记录
+pub fn write_records<W: Write>(
+    records: impl Iterator<Item = Result<Value>>,
+    writer: W,
+    output_format: OutputFormat,
+) -> Result<()> {
+    match output_format {
+        OutputFormat::Json => {
+            let mut ser = serde_json::Serializer::pretty(writer);
+            write_seq(&mut ser, records)
+        }
+        OutputFormat::Yaml => {
+            let mut ser = serde_yaml::Serializer::new(writer);
+            write_seq(&mut ser, records)
+        }
+        OutputFormat::Ndjson => write_ndjson(records, writer),
+        OutputFormat::Toml => write_toml(records, writer),
+        OutputFormat::Xml => write_xml(records, writer),
+        OutputFormat::Csv => write_csv(records, writer),
+    }
+}
+
+fn write_seq<S>(ser: S, records: impl Iterator<Item = Result<Value>>) -> Result<()>
+where
+    S: Serializer,
+    S::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut seq = ser.serialize_seq(None)?;
+    for record in records {
+        seq.serialize_element(&record?)?;
+    }
+    seq.end()?;
+
+    Ok(())
+}
+
+fn write_ndjson<W: Write>(
+    records: impl Iterator<Item = Result<Value>>,
+    mut writer: W,
+) -> Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut writer, &record?)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+// TOML 顶层必须是表, 每条记录作为 [[records]] 数组表的一项写出
+fn write_toml<W: Write>(records: impl Iterator<Item = Result<Value>>, mut writer: W) -> Result<()> {
+    for (i, record) in records.enumerate() {
+        let mut table = Map::new();
+        table.insert("records".into(), Value::Array(vec![strip_nulls(record?)]));
+        if i > 0 {
+            writer.write_all(b"\n")?;
+        }
+        writer.write_all(toml::to_string(&table)?.as_bytes())?;
+    }
+    Ok(())
+}
+
+// TOML 不支持 null, 直接丢弃对应的键
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, strip_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .filter(|v| !v.is_null())
+                .map(strip_nulls)
+                .collect(),
+        ),
+        v => v,
+    }
+}
+
+fn write_xml<W: Write>(records: impl Iterator<Item = Result<Value>>, mut writer: W) -> Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, "<records>")?;
+    for record in records {
+        write_xml_element(&mut writer, "record", &record?, 1)?;
+    }
+    writeln!(writer, "</records>")?;
+    Ok(())
+}
+
+fn write_xml_element<W: Write>(
+    writer: &mut W,
+    name: &str,
+    value: &Value,
+    depth: usize,
+) -> Result<()> {
+    let indent = "  ".repeat(depth);
+    let tag = xml_name(name);
+    match value {
+        Value::Null => writeln!(writer, "{}<{}/>", indent, tag)?,
+        Value::Object(map) => {
+            writeln!(writer, "{}<{}>", indent, tag)?;
+            for (k, v) in map {
+                write_xml_element(writer, k, v, depth + 1)?;
+            }
+            writeln!(writer, "{}</{}>", indent, tag)?;
+        }
+        // 数组展开为多个同名元素
+        Value::Array(items) => {
+            for item in items {
+                write_xml_element(writer, name, item, depth)?;
+            }
+        }
+        v => writeln!(
+            writer,
+            "{}<{}>{}</{}>",
+            indent,
+            tag,
+            xml_escape(&scalar_to_string(v)),
+            tag
+        )?,
+    }
+    Ok(())
+}
+
+// 表头可能包含空格等非法字符, 替换为下划线
+fn xml_name(name: &str) -> String {
+    let mut ret: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !ret.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        ret.insert(0, '_');
+    }
+    ret
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            '\'' => ret.push_str("&apos;"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+// 以第一条记录的键作为表头, 之后的记录按表头顺序取值; 嵌套字段展开为 a.b / a[0]
+// 表头写出后无法再补充列, 之后出现新键时报错而不是丢弃数据
+fn write_csv<W: Write>(records: impl Iterator<Item = Result<Value>>, writer: W) -> Result<()> {
+    let mut writer = Writer::from_writer(writer);
+    let mut headers: Option<Vec<String>> = None;
+    for (i, record) in records.enumerate() {
+        let map = flatten(record?);
+        let headers = match &headers {
+            Some(headers) => headers,
+            None => {
+                writer.write_record(map.keys())?;
+                headers.insert(map.keys().cloned().collect())
+            }
+        };
+        if let Some(key) = map.keys().find(|k| !headers.contains(k)) {
+            anyhow::bail!(
+                "Record {} has field {:?} which is not in the csv header {:?}, \
+                 use a format other than csv to keep it",
+                i + 1,
+                key,
+                headers
+            );
+        }
+        writer.write_record(
+            headers
+                .iter()
+                .map(|h| map.get(h).map(scalar_to_string).unwrap_or_default()),
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub(crate) fn scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
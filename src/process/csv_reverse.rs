@@ -0,0 +1,132 @@
+use anyhow::Result;
+use csv::WriterBuilder;
+use serde_json::{Map, Value};
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader, Read, Write},
+};
+
+use super::{csv_output::scalar_to_string, flatten::flatten};
+use crate::{read_data, ColumnOrder, InputFormat};
+
+pub fn process_csv_reverse(
+    input: &str,
+    writer: impl Write,
+    input_format: InputFormat,
+    order: ColumnOrder,
+    columns: &[String],
+    delimiter: u8,
+) -> Result<()> {
+    let reader = read_data(input)?;
+    reverse_records(reader, writer, input_format, order, columns, delimiter)
+}
+
+fn reverse_records<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    input_format: InputFormat,
+    order: ColumnOrder,
+    columns: &[String],
+    delimiter: u8,
+) -> Result<()> {
+    // 表头需要所有记录的键的并集, 因此先完整读入
+    let records = read_records(reader, input_format)?
+        .into_iter()
+        .map(flatten)
+        .collect::<Vec<_>>();
+    let headers = if columns.is_empty() {
+        union_headers(&records, order)
+    } else {
+        columns.to_vec()
+    };
+
+    let mut writer = WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(writer);
+    writer.write_record(&headers)?;
+    for record in &records {
+        writer.write_record(
+            headers
+                .iter()
+                .map(|h| record.get(h).map(scalar_to_string).unwrap_or_default()),
+        )?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn read_records<R: Read>(reader: R, input_format: InputFormat) -> Result<Vec<Value>> {
+    let value: Value = match input_format {
+        InputFormat::Json => serde_json::from_reader(reader)?,
+        InputFormat::Yaml => serde_yaml::from_reader(reader)?,
+        InputFormat::Ndjson => {
+            let mut ret = Vec::new();
+            for line in BufReader::new(reader).lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    ret.push(serde_json::from_str(&line)?);
+                }
+            }
+            Value::Array(ret)
+        }
+    };
+
+    match value {
+        Value::Array(items) => Ok(items),
+        Value::Object(_) => Ok(vec![value]),
+        v => anyhow::bail!("Expect an array of objects, got {}", v),
+    }
+}
+
+fn union_headers(records: &[Map<String, Value>], order: ColumnOrder) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut headers = Vec::new();
+    for key in records.iter().flat_map(|r| r.keys()) {
+        if seen.insert(key.as_str()) {
+            headers.push(key.clone());
+        }
+    }
+    if let ColumnOrder::Sorted = order {
+        headers.sort();
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reverse(input: &str, format: InputFormat, order: ColumnOrder) -> Result<String> {
+        let mut buf = Vec::new();
+        reverse_records(input.as_bytes(), &mut buf, format, order, &[], b',')?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    #[test]
+    fn test_reverse_records_union_headers() -> Result<()> {
+        let input = r#"[
+            { "Name": "Buffon", "Kit Number": 77 },
+            { "Name": "Perin", "Nationality": "Italy", "address": { "city": "Genoa" } }
+        ]"#;
+        let ret = reverse(input, InputFormat::Json, ColumnOrder::FirstSeen)?;
+        let expected = "\
+Name,Kit Number,Nationality,address.city
+Buffon,77,,
+Perin,,Italy,Genoa
+";
+        assert_eq!(ret, expected);
+
+        let ret = reverse(input, InputFormat::Json, ColumnOrder::Sorted)?;
+        assert!(ret.starts_with("Kit Number,Name,Nationality,address.city\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reverse_records_ndjson() -> Result<()> {
+        let input = "{\"a\":1,\"b\":[true,null]}\n\n{\"a\":2}\n";
+        let ret = reverse(input, InputFormat::Ndjson, ColumnOrder::FirstSeen)?;
+        assert_eq!(ret, "a,b[0],b[1]\n1,true,\n2,,\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,318 @@
+use anyhow::Result;
+use csv::{ReaderBuilder, StringRecord, StringRecordsIntoIter, WriterBuilder};
+use serde_json::Value;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, Write},
+    str::FromStr,
+};
+
+use super::{
+    csv_convert::{column_indices, record_to_value},
+    csv_output::write_records,
+};
+use crate::{read_data, CsvReaderConfig, OutputFormat};
+
+// 归并时最多同时打开的临时文件数
+const MERGE_FAN_IN: usize = 64;
+
+// `--by col[:desc]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: String,
+    pub desc: bool,
+}
+
+pub fn process_csv_sort(
+    input: &str,
+    mut writer: impl Write,
+    keys: &[SortKey],
+    chunk_rows: usize,
+    config: &CsvReaderConfig,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let reader = read_data(input)?;
+    sort_records(reader, &mut writer, keys, chunk_rows, config, output_format)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+// 外部归并排序: 每 chunk_rows 行排序后写入临时文件, 最后多路归并
+fn sort_records<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    keys: &[SortKey],
+    chunk_rows: usize,
+    config: &CsvReaderConfig,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let mut reader = config.reader(reader)?;
+    let headers = config.headers(&mut reader)?;
+    let columns = keys.iter().map(|k| k.column.clone()).collect::<Vec<_>>();
+    let keys = column_indices(&headers, &columns)?
+        .into_iter()
+        .zip(keys.iter().map(|k| k.desc))
+        .collect::<Vec<_>>();
+    let to_value = |record: Result<StringRecord>| {
+        record_to_value(&headers, &[], &record?, false).map(Value::Object)
+    };
+
+    let chunk_rows = chunk_rows.max(1);
+    let mut records = reader.into_records();
+    let mut chunks = Vec::new();
+    loop {
+        let mut chunk = records
+            .by_ref()
+            .take(chunk_rows)
+            .collect::<Result<Vec<_>, _>>()?;
+        chunk.sort_by(|a, b| compare_records(a, b, &keys));
+        let last = chunk.len() < chunk_rows;
+        // 能一次放入内存时不使用临时文件
+        if chunks.is_empty() && last {
+            let records = chunk.into_iter().map(Ok).map(to_value);
+            return write_records(records, writer, output_format);
+        }
+        if !chunk.is_empty() {
+            chunks.push(spill_chunk(chunk.into_iter().map(Ok))?);
+        }
+        if last {
+            break;
+        }
+    }
+
+    let records = merge_chunks(chunks, &keys, MERGE_FAN_IN)?.map(to_value);
+    write_records(records, writer, output_format)
+}
+
+fn spill_chunk(records: impl Iterator<Item = Result<StringRecord>>) -> Result<File> {
+    let mut writer = WriterBuilder::new()
+        .flexible(true)
+        .from_writer(BufWriter::new(tempfile::tempfile()?));
+    for record in records {
+        writer.write_record(&record?)?;
+    }
+    let mut file = writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .into_inner()
+        .map_err(|e| e.into_error())?;
+    file.rewind()?;
+    Ok(file)
+}
+
+// 同时打开的临时文件不超过 fan_in 个, 超出时先把相邻的若干路归并成一个新文件;
+// 只归并相邻的文件, 相等的记录仍按原始顺序输出
+fn merge_chunks<'a>(
+    mut chunks: Vec<File>,
+    keys: &'a [(usize, bool)],
+    fan_in: usize,
+) -> Result<impl Iterator<Item = Result<StringRecord>> + 'a> {
+    let fan_in = fan_in.max(2);
+    while chunks.len() > fan_in {
+        let mut merged = Vec::with_capacity(chunks.len().div_ceil(fan_in));
+        let mut rest = chunks.into_iter();
+        loop {
+            let group = rest.by_ref().take(fan_in).collect::<Vec<_>>();
+            if group.is_empty() {
+                break;
+            }
+            merged.push(spill_chunk(MergeIter::new(group, keys)?)?);
+        }
+        chunks = merged;
+    }
+    MergeIter::new(chunks, keys)
+}
+
+// 用小顶堆取各路首条记录中的最小值, 相等时取序号小的一路
+struct MergeIter<'a> {
+    sources: Vec<StringRecordsIntoIter<BufReader<File>>>,
+    heap: BinaryHeap<Reverse<MergeHead<'a>>>,
+}
+
+struct MergeHead<'a> {
+    record: StringRecord,
+    source: usize,
+    keys: &'a [(usize, bool)],
+}
+
+impl<'a> MergeIter<'a> {
+    fn new(chunks: Vec<File>, keys: &'a [(usize, bool)]) -> Result<Self> {
+        let mut sources = chunks
+            .into_iter()
+            .map(|file| {
+                ReaderBuilder::new()
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_reader(BufReader::new(file))
+                    .into_records()
+            })
+            .collect::<Vec<_>>();
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+        for (source, records) in sources.iter_mut().enumerate() {
+            if let Some(record) = records.next().transpose()? {
+                heap.push(Reverse(MergeHead {
+                    record,
+                    source,
+                    keys,
+                }));
+            }
+        }
+        Ok(Self { sources, heap })
+    }
+}
+
+impl Iterator for MergeIter<'_> {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse(head) = self.heap.pop()?;
+        match self.sources[head.source].next().transpose() {
+            Ok(Some(record)) => self.heap.push(Reverse(MergeHead {
+                record,
+                source: head.source,
+                keys: head.keys,
+            })),
+            Ok(None) => {}
+            Err(e) => return Some(Err(e.into())),
+        }
+        Some(Ok(head.record))
+    }
+}
+
+impl Ord for MergeHead<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_records(&self.record, &other.record, self.keys).then(self.source.cmp(&other.source))
+    }
+}
+
+impl PartialOrd for MergeHead<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MergeHead<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for MergeHead<'_> {}
+
+fn compare_records(a: &StringRecord, b: &StringRecord, keys: &[(usize, bool)]) -> Ordering {
+    keys.iter()
+        .map(|&(i, desc)| {
+            let ordering =
+                compare_fields(a.get(i).unwrap_or_default(), b.get(i).unwrap_or_default());
+            if desc {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+// 数字按数值比较并排在字符串之前, 保证是全序
+fn compare_fields(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let (column, desc) = match key.rsplit_once(':') {
+            Some((column, order)) => match order.to_lowercase().as_str() {
+                "asc" => (column, false),
+                "desc" => (column, true),
+                v => anyhow::bail!("Unsupported sort order {:?}, expect asc or desc", v),
+            },
+            None => (key, false),
+        };
+        if column.is_empty() {
+            anyhow::bail!("Sort column must not be empty");
+        }
+        Ok(SortKey {
+            column: column.to_string(),
+            desc,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str =
+        "Name,Kit Number\nBuffon,77\nChiellini,3\nPerin,37\nDybala,10\nBonucci,19\n";
+
+    fn sort(keys: &str, chunk_rows: usize) -> Result<String> {
+        let keys = keys
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<SortKey>>>()?;
+        let mut buf = Vec::new();
+        sort_records(
+            INPUT.as_bytes(),
+            &mut buf,
+            &keys,
+            chunk_rows,
+            &CsvReaderConfig::default(),
+            OutputFormat::Csv,
+        )?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    #[test]
+    fn test_sort_key_parse() -> Result<()> {
+        let key: SortKey = "Kit Number:desc".parse()?;
+        assert_eq!(key.column, "Kit Number");
+        assert!(key.desc);
+        assert!(!"Name".parse::<SortKey>()?.desc);
+        assert!("Name:down".parse::<SortKey>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_records() -> Result<()> {
+        let expected = "Name,Kit Number\nBuffon,77\nPerin,37\nBonucci,19\nDybala,10\nChiellini,3\n";
+        assert_eq!(sort("Kit Number:desc", 100)?, expected);
+        // 每 2 行一个临时文件, 结果应与内存排序一致
+        assert_eq!(sort("Kit Number:desc", 2)?, expected);
+        assert_eq!(sort("Name", 2)?, sort("Name", 100)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_chunks_multi_pass() -> Result<()> {
+        // 7 个临时文件, 每次最多归并 2 个, 需要多轮; 相同的键保持原始顺序
+        let rows = ["b,1", "a,2", "b,3", "a,4", "c,5", "a,6", "b,7"];
+        let chunks = rows
+            .iter()
+            .map(|row| spill_chunk(std::iter::once(Ok(row.split(',').collect()))))
+            .collect::<Result<Vec<_>>>()?;
+        let keys = [(0, false)];
+        let merged = merge_chunks(chunks, &keys, 2)?
+            .map(|record| Ok(record?.get(1).unwrap_or_default().to_string()))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(merged, ["2", "4", "6", "1", "3", "7", "5"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_fields() {
+        assert_eq!(compare_fields("9", "10"), Ordering::Less);
+        assert_eq!(compare_fields("10", "abc"), Ordering::Less);
+        assert_eq!(compare_fields("b", "a"), Ordering::Greater);
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::Result;
+use csv::{StringRecord, Writer, WriterBuilder};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs::{File, OpenOptions},
+    io::{BufWriter, Read},
+    path::{Path, PathBuf},
+};
+
+use super::csv_convert::column_indices;
+use crate::{read_data, CsvReaderConfig};
+
+// 按列拆分时最多同时打开的输出文件数
+const MAX_OPEN_FILES: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum SplitBy {
+    Rows(usize),
+    Column(String),
+}
+
+// 输出 <prefix>_0001.csv 或 <prefix>_<列值>.csv, 每个文件都带表头, 返回生成的文件
+pub fn process_csv_split(
+    input: &str,
+    output_dir: &Path,
+    prefix: Option<&str>,
+    by: &SplitBy,
+    config: &CsvReaderConfig,
+) -> Result<Vec<PathBuf>> {
+    let path = Path::new(input);
+    let prefix = match prefix {
+        Some(prefix) => prefix.to_string(),
+        None if input == "-" => "stdin".into(),
+        None => path
+            .file_stem()
+            .map_or_else(|| "chunk".into(), |s| s.to_string_lossy().into()),
+    };
+    let extension = path
+        .extension()
+        .map_or_else(|| "csv".into(), |s| s.to_string_lossy().to_string());
+    let reader = read_data(input)?;
+    split_records(reader, by, config, MAX_OPEN_FILES, |key| {
+        output_dir.join(format!("{}_{}.{}", prefix, key, extension))
+    })
+}
+
+fn split_records<R: Read>(
+    reader: R,
+    by: &SplitBy,
+    config: &CsvReaderConfig,
+    max_open: usize,
+    path_of: impl Fn(&str) -> PathBuf,
+) -> Result<Vec<PathBuf>> {
+    let mut reader = config.reader(reader)?;
+    let headers = config.headers(&mut reader)?;
+    let column = match by {
+        SplitBy::Rows(0) => anyhow::bail!("Rows per file must be greater than 0"),
+        SplitBy::Rows(_) => 0,
+        SplitBy::Column(column) => column_indices(&headers, std::slice::from_ref(column))?[0],
+    };
+
+    let mut writers = WriterCache {
+        config,
+        headers: &headers,
+        max_open: max_open.max(1),
+        writers: HashMap::new(),
+        tick: 0,
+    };
+    // 忽略大小写的文件名 -> 对应的原始值, 大小写不敏感的文件系统上它们是同一个文件
+    let mut values: HashMap<String, String> = HashMap::new();
+    let mut files = Vec::new();
+    for (i, record) in reader.into_records().enumerate() {
+        let record = record?;
+        let (key, value) = match by {
+            SplitBy::Rows(rows) => {
+                if i % rows == 0 {
+                    writers.close_all()?;
+                }
+                let key = format!("{:04}", i / rows + 1);
+                (key.clone(), key)
+            }
+            SplitBy::Column(_) => {
+                let value = record.get(column).unwrap_or_default();
+                (file_key(value), value.to_string())
+            }
+        };
+        let append = match values.entry(key.to_lowercase()) {
+            Entry::Occupied(entry) if *entry.get() != value => anyhow::bail!(
+                "Values {:?} and {:?} would be written to the same file {:?}",
+                entry.get(),
+                value,
+                path_of(&key)
+            ),
+            Entry::Occupied(_) => true,
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                files.push(path_of(&key));
+                false
+            }
+        };
+        writers.write(&key, &record, append, &path_of)?;
+    }
+    writers.close_all()?;
+
+    Ok(files)
+}
+
+// 按列拆分时列值可能很多, 最多同时打开 max_open 个文件;
+// 超出时关闭最久未写入的文件, 之后再写入时以追加方式重新打开
+struct WriterCache<'a> {
+    config: &'a CsvReaderConfig,
+    headers: &'a StringRecord,
+    max_open: usize,
+    writers: HashMap<String, (Writer<BufWriter<File>>, usize)>,
+    tick: usize,
+}
+
+impl WriterCache<'_> {
+    fn write(
+        &mut self,
+        key: &str,
+        record: &StringRecord,
+        append: bool,
+        path_of: impl Fn(&str) -> PathBuf,
+    ) -> Result<()> {
+        self.tick += 1;
+        if !self.writers.contains_key(key) && self.writers.len() >= self.max_open {
+            self.evict()?;
+        }
+        let (writer, used) = match self.writers.entry(key.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let writer = open_writer(&path_of(key), self.config, self.headers, append)?;
+                entry.insert((writer, 0))
+            }
+        };
+        *used = self.tick;
+        writer.write_record(record)?;
+        Ok(())
+    }
+
+    fn evict(&mut self) -> Result<()> {
+        let oldest = self
+            .writers
+            .iter()
+            .min_by_key(|(_, (_, used))| *used)
+            .map(|(key, _)| key.clone());
+        if let Some((mut writer, _)) = oldest.and_then(|key| self.writers.remove(&key)) {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn close_all(&mut self) -> Result<()> {
+        for (_, (mut writer, _)) in self.writers.drain() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn open_writer(
+    path: &Path,
+    config: &CsvReaderConfig,
+    headers: &StringRecord,
+    append: bool,
+) -> Result<Writer<BufWriter<File>>> {
+    let file = if append {
+        OpenOptions::new().append(true).open(path)?
+    } else {
+        File::create(path)?
+    };
+    let mut writer = WriterBuilder::new()
+        .delimiter(config.delimiter)
+        .quote(config.quote)
+        .flexible(config.flexible)
+        .from_writer(BufWriter::new(file));
+    // 无表头的输入拆分后同样不带表头
+    if config.has_headers && !append {
+        writer.write_record(headers)?;
+    }
+    Ok(writer)
+}
+
+// 列值中不能用于文件名的字符替换为 _
+fn file_key(value: &str) -> String {
+    let key = value
+        .chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '.' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    match key.trim_matches('.') {
+        "" => "empty".into(),
+        key => key.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_split_records() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = CsvReaderConfig::default();
+        let files = process_csv_split(
+            "assets/juventus.csv",
+            dir.path(),
+            None,
+            &SplitBy::Rows(10),
+            &config,
+        )?;
+        assert_eq!(files.len(), 3);
+        assert!(files[0].ends_with("juventus_0001.csv"));
+        let last = fs::read_to_string(&files[2])?;
+        assert!(last.starts_with("Name,Position,DOB,Nationality,Kit Number\n"));
+        assert_eq!(last.lines().count(), 8);
+
+        let files = process_csv_split(
+            "assets/juventus.csv",
+            dir.path(),
+            Some("team"),
+            &SplitBy::Column("Nationality".into()),
+            &config,
+        )?;
+        let italy = fs::read_to_string(dir.path().join("team_Italy.csv"))?;
+        assert_eq!(italy.lines().count(), 9);
+        assert!(files.contains(&dir.path().join("team_Bosnia-Herzegovina.csv")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_records_reopen_and_collision() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = CsvReaderConfig::default();
+        let path_of = |key: &str| dir.path().join(format!("{}.csv", key));
+        let by = SplitBy::Column("Country".into());
+
+        // 最多打开 2 个文件, 第三个值出现时需要关闭并重新打开
+        let input = "Name,Country\na,Italy\nb,France\nc,Spain\nd,Italy\ne,France\n";
+        let files = split_records(input.as_bytes(), &by, &config, 2, path_of)?;
+        assert_eq!(files.len(), 3);
+        let italy = fs::read_to_string(path_of("Italy"))?;
+        assert_eq!(italy, "Name,Country\na,Italy\nd,Italy\n");
+        let france = fs::read_to_string(path_of("France"))?;
+        assert_eq!(france, "Name,Country\nb,France\ne,France\n");
+
+        let input = "Name,Country\na,Italy\nb,italy\n";
+        assert!(split_records(input.as_bytes(), &by, &config, 2, path_of).is_err());
+        let input = "Name,Country\na,New Zealand\nb,New/Zealand\n";
+        assert!(split_records(input.as_bytes(), &by, &config, 2, path_of).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_key() {
+        assert_eq!(file_key("Centre Back/2"), "Centre_Back_2");
+        assert_eq!(file_key(".."), "empty");
+        assert_eq!(file_key("Côte d'Ivoire"), "Côte_d_Ivoire");
+    }
+}
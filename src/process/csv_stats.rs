@@ -0,0 +1,264 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use super::{csv_convert::column_name, csv_output::write_records};
+use crate::{read_data, ColumnType, CsvReaderConfig, OutputFormat};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnStats {
+    pub column: String,
+    #[serde(rename = "type")]
+    pub column_type: ColumnType,
+    pub count: usize,
+    pub nulls: usize,
+    pub distinct: usize,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub mean: Option<f64>,
+    pub top: Vec<ValueCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueCount {
+    pub value: String,
+    pub count: usize,
+}
+
+#[derive(Debug)]
+struct ColumnProfile {
+    column_type: ColumnType,
+    count: usize,
+    nulls: usize,
+    sum: f64,
+    min_num: Option<f64>,
+    max_num: Option<f64>,
+    min_str: Option<String>,
+    max_str: Option<String>,
+    // 精确统计 distinct 与 top-N, 内存与列的基数成正比
+    counts: HashMap<String, usize>,
+}
+
+// output_format 为 None 时输出对齐的文本表格
+pub fn process_csv_stats(
+    input: &str,
+    mut writer: impl Write,
+    config: &CsvReaderConfig,
+    top: usize,
+    output_format: Option<OutputFormat>,
+) -> Result<()> {
+    let reader = read_data(input)?;
+    let stats = profile_records(reader, config, top)?;
+    match output_format {
+        Some(output_format) => {
+            let records = stats.iter().map(|s| Ok(serde_json::to_value(s)?));
+            write_records(records, &mut writer, output_format)?;
+        }
+        None => write_table(&stats, &mut writer)?,
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write_table(stats: &[ColumnStats], writer: &mut impl Write) -> Result<()> {
+    let headers = [
+        "column", "type", "count", "nulls", "distinct", "min", "max", "mean", "top",
+    ];
+    let rows = stats
+        .iter()
+        .map(|s| {
+            let top = s
+                .top
+                .iter()
+                .map(|v| format!("{}({})", v.value, v.count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            vec![
+                s.column.clone(),
+                s.column_type.to_string(),
+                s.count.to_string(),
+                s.nulls.to_string(),
+                s.distinct.to_string(),
+                s.min.clone().unwrap_or_default(),
+                s.max.clone().unwrap_or_default(),
+                s.mean.map(|m| format!("{:.2}", m)).unwrap_or_default(),
+                top,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = headers.map(|h| h.chars().count());
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let headers = headers.map(String::from).to_vec();
+    for row in std::iter::once(&headers).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, w)| format!("{:<w$}", cell, w = w))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(writer, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+fn profile_records<R: Read>(
+    reader: R,
+    config: &CsvReaderConfig,
+    top: usize,
+) -> Result<Vec<ColumnStats>> {
+    let mut reader = config.reader(reader)?;
+    let headers = config.headers(&mut reader)?;
+    let mut profiles: Vec<ColumnProfile> = Vec::with_capacity(headers.len());
+
+    for record in reader.into_records() {
+        let record = record?;
+        for (i, field) in record.iter().enumerate() {
+            if profiles.len() <= i {
+                profiles.resize_with(i + 1, ColumnProfile::new);
+            }
+            profiles[i].update(field);
+        }
+    }
+    profiles.resize_with(headers.len().max(profiles.len()), ColumnProfile::new);
+
+    let stats = profiles
+        .into_iter()
+        .enumerate()
+        .map(|(i, profile)| {
+            let column = headers.get(i).map_or_else(|| column_name(i), String::from);
+            profile.finish(column, top)
+        })
+        .collect();
+    Ok(stats)
+}
+
+impl ColumnProfile {
+    fn new() -> Self {
+        Self {
+            column_type: ColumnType::Null,
+            count: 0,
+            nulls: 0,
+            sum: 0.0,
+            min_num: None,
+            max_num: None,
+            min_str: None,
+            max_str: None,
+            counts: HashMap::new(),
+        }
+    }
+
+    fn update(&mut self, field: &str) {
+        self.count += 1;
+        let t = ColumnType::infer(field);
+        self.column_type = self.column_type.merge(t);
+        if t == ColumnType::Null {
+            self.nulls += 1;
+            return;
+        }
+
+        if let Ok(n) = field.parse::<f64>() {
+            self.sum += n;
+            self.min_num = Some(self.min_num.map_or(n, |m| m.min(n)));
+            self.max_num = Some(self.max_num.map_or(n, |m| m.max(n)));
+        }
+        if self.min_str.as_deref().is_none_or(|m| field < m) {
+            self.min_str = Some(field.to_string());
+        }
+        if self.max_str.as_deref().is_none_or(|m| field > m) {
+            self.max_str = Some(field.to_string());
+        }
+        *self.counts.entry(field.to_string()).or_default() += 1;
+    }
+
+    fn finish(self, column: String, top: usize) -> ColumnStats {
+        let numeric = matches!(self.column_type, ColumnType::Integer | ColumnType::Float);
+        let (min, max, mean) = if numeric {
+            let non_null = (self.count - self.nulls) as f64;
+            (
+                self.min_num.map(|n| n.to_string()),
+                self.max_num.map(|n| n.to_string()),
+                Some(self.sum / non_null),
+            )
+        } else {
+            (self.min_str, self.max_str, None)
+        };
+
+        let distinct = self.counts.len();
+        let mut counts = self
+            .counts
+            .into_iter()
+            .map(|(value, count)| ValueCount { value, count })
+            .collect::<Vec<_>>();
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        counts.truncate(top);
+
+        ColumnStats {
+            column,
+            column_type: self.column_type,
+            count: self.count,
+            nulls: self.nulls,
+            distinct,
+            min,
+            max,
+            mean,
+            top: counts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_juventus() -> Result<()> {
+        let reader = std::fs::File::open("assets/juventus.csv")?;
+        let stats = profile_records(reader, &CsvReaderConfig::default(), 2)?;
+        assert_eq!(stats.len(), 5);
+
+        let nationality = &stats[3];
+        assert_eq!(nationality.column, "Nationality");
+        assert_eq!(nationality.column_type, ColumnType::String);
+        assert_eq!(nationality.count, 27);
+        assert_eq!(
+            nationality.top[0],
+            ValueCount {
+                value: "Italy".into(),
+                count: 8
+            }
+        );
+
+        let kit = &stats[4];
+        assert_eq!(kit.column_type, ColumnType::Integer);
+        assert_eq!(kit.nulls, 0);
+        assert_eq!(kit.distinct, 27);
+        assert_eq!(kit.min.as_deref(), Some("1"));
+        assert_eq!(kit.max.as_deref(), Some("77"));
+        assert!(kit.mean.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_table() -> Result<()> {
+        let input = "Name,Kit Number\nBuffon,77\nPerin,\n";
+        let stats = profile_records(input.as_bytes(), &CsvReaderConfig::default(), 1)?;
+        let mut buf = Vec::new();
+        write_table(&stats, &mut buf)?;
+        let expected = "\
+column      type     count  nulls  distinct  min     max    mean   top
+Name        string   2      0      2         Buffon  Perin         Buffon(1)
+Kit Number  integer  2      1      1         77      77     77.00  77(1)
+";
+        assert_eq!(String::from_utf8(buf)?, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::Result;
+use csv::StringRecord;
+use jsonschema::JSONSchema;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fmt, fs, io::Read, path::Path};
+
+use super::csv_convert::column_name;
+use crate::{read_data, ColumnType, CsvReaderConfig};
+
+pub enum ValidationSpec {
+    // 简单的 列名 -> 类型 映射
+    Columns(HashMap<String, ColumnType>),
+    // JSON Schema, 字段按 properties 中声明的类型转换后再校验
+    JsonSchema {
+        schema: Box<JSONSchema>,
+        types: HashMap<String, ColumnType>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationFailure {
+    pub line: u64,
+    pub column: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub rows: usize,
+    pub invalid_rows: usize,
+    // 不受 max_errors 限制, 保证缺列时仍判定为失败
+    pub missing_columns: Vec<String>,
+    pub failures: Vec<ValidationFailure>,
+}
+
+pub fn process_csv_validate(
+    input: &str,
+    config: &CsvReaderConfig,
+    spec: &ValidationSpec,
+    max_errors: usize,
+) -> Result<ValidationReport> {
+    let reader = read_data(input)?;
+    validate_records(reader, config, spec, max_errors)
+}
+
+fn validate_records<R: Read>(
+    reader: R,
+    config: &CsvReaderConfig,
+    spec: &ValidationSpec,
+    max_errors: usize,
+) -> Result<ValidationReport> {
+    let mut reader = config.reader(reader)?;
+    let headers = config.headers(&mut reader)?;
+    let mut report = ValidationReport::default();
+
+    if let ValidationSpec::Columns(columns) = spec {
+        for column in columns.keys() {
+            if !headers.iter().any(|h| h == column) {
+                report.missing_columns.push(column.clone());
+                report.push(max_errors, 1, column, "column is missing".into());
+            }
+        }
+    }
+
+    for record in reader.into_records() {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line());
+        let failures = spec.validate(&headers, &record);
+        report.rows += 1;
+        if !failures.is_empty() {
+            report.invalid_rows += 1;
+        }
+        for (column, reason) in failures {
+            report.push(max_errors, line, &column, reason);
+        }
+    }
+
+    Ok(report)
+}
+
+impl ValidationSpec {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let value: Value = serde_yaml::from_str(&content)?;
+        Self::from_value(value)
+    }
+
+    // 带 $schema, 或者 type 为 object / properties 为对象时按 JSON Schema 处理, 其余按 列名 -> 类型;
+    // 不能先试一种再退回另一种: JSON Schema 会忽略拼错的类型, 导致所有行都通过校验
+    fn from_value(value: Value) -> Result<Self> {
+        let is_schema = value.get("$schema").is_some()
+            || value.get("type").is_some_and(|t| t == "object")
+            || value.get("properties").is_some_and(Value::is_object);
+        if is_schema {
+            return Self::try_new_json_schema(&value);
+        }
+        let columns = serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("Invalid column types: {}", e))?;
+        Ok(ValidationSpec::Columns(columns))
+    }
+
+    pub fn try_new_json_schema(schema: &Value) -> Result<Self> {
+        let types = schema["properties"]
+            .as_object()
+            .map(|props| {
+                props
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.clone(), schema_type(&v["type"])?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let schema = JSONSchema::compile(schema)
+            .map_err(|e| anyhow::anyhow!("Invalid JSON schema: {}", e))?;
+        Ok(ValidationSpec::JsonSchema {
+            schema: Box::new(schema),
+            types,
+        })
+    }
+
+    // 返回 (列名, 原因) 列表
+    fn validate(&self, headers: &StringRecord, record: &StringRecord) -> Vec<(String, String)> {
+        let fields = headers
+            .iter()
+            .map(String::from)
+            .chain((headers.len()..).map(column_name))
+            .zip(record.iter());
+        match self {
+            ValidationSpec::Columns(columns) => fields
+                .filter_map(|(column, field)| {
+                    let t = columns.get(&column)?;
+                    match t.parse(field) {
+                        Some(_) => None,
+                        None => Some((column, format!("{:?} is not a valid {}", field, t))),
+                    }
+                })
+                .collect(),
+            ValidationSpec::JsonSchema { schema, types } => {
+                let value = fields
+                    .map(|(column, field)| {
+                        let value = types
+                            .get(&column)
+                            .and_then(|t| t.parse(field))
+                            .unwrap_or_else(|| Value::String(field.to_string()));
+                        (column, value)
+                    })
+                    .collect::<Map<_, _>>();
+                let value = Value::Object(value);
+                // 错误迭代器借用了 value, 需先收集再返回
+                let failures = match schema.validate(&value) {
+                    Ok(()) => Vec::new(),
+                    Err(errors) => errors
+                        .map(|e| {
+                            let column = e.instance_path.clone().into_vec();
+                            (column.join("."), e.to_string())
+                        })
+                        .collect(),
+                };
+                failures
+            }
+        }
+    }
+}
+
+// JSON Schema 的 type 可能是数组, 取第一个非 null 的类型
+fn schema_type(t: &Value) -> Option<ColumnType> {
+    let t = match t {
+        Value::Array(types) => types.iter().find(|t| *t != "null")?,
+        t => t,
+    };
+    match t.as_str()? {
+        "integer" => Some(ColumnType::Integer),
+        "number" => Some(ColumnType::Float),
+        "boolean" => Some(ColumnType::Boolean),
+        "null" => Some(ColumnType::Null),
+        _ => None,
+    }
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.invalid_rows == 0 && self.missing_columns.is_empty() && self.failures.is_empty()
+    }
+
+    fn push(&mut self, max_errors: usize, line: u64, column: &str, reason: String) {
+        if self.failures.len() < max_errors {
+            self.failures.push(ValidationFailure {
+                line,
+                column: column.to_string(),
+                reason,
+            });
+        }
+    }
+}
+
+impl fmt::Display for ValidationFailure {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "line {}, column {:?}: {}",
+            self.line, self.column, self.reason
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const INPUT: &str = "Name,Kit Number\nBuffon,77\nPerin,x\n,31\n";
+
+    fn validate(spec: &ValidationSpec) -> Result<ValidationReport> {
+        validate_records(INPUT.as_bytes(), &CsvReaderConfig::default(), spec, 100)
+    }
+
+    #[test]
+    fn test_validate_records_columns() -> Result<()> {
+        let columns = [
+            ("Kit Number".to_string(), ColumnType::Integer),
+            ("Club".to_string(), ColumnType::String),
+        ];
+        let report = validate(&ValidationSpec::Columns(columns.into()))?;
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.invalid_rows, 1);
+        assert_eq!(report.failures.len(), 2);
+        assert!(report.failures.contains(&ValidationFailure {
+            line: 3,
+            column: "Kit Number".into(),
+            reason: "\"x\" is not a valid integer".into(),
+        }));
+        assert_eq!(report.missing_columns, ["Club"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_records_missing_column_without_errors() -> Result<()> {
+        let spec = ValidationSpec::Columns([("Club".to_string(), ColumnType::String)].into());
+        let report = validate_records(INPUT.as_bytes(), &CsvReaderConfig::default(), &spec, 0)?;
+        assert!(report.failures.is_empty());
+        assert_eq!(report.invalid_rows, 0);
+        assert!(!report.is_valid());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validation_spec_from_value() {
+        let spec = ValidationSpec::from_value(json!({ "Kit Number": "integer", "type": "string" }));
+        assert!(matches!(spec, Ok(ValidationSpec::Columns(_))));
+        let spec = ValidationSpec::from_value(json!({ "Kit Number": "integr" }));
+        let Err(e) = spec else {
+            panic!("typo in a column type must be rejected");
+        };
+        assert!(e.to_string().contains("integr"));
+        let spec = ValidationSpec::from_value(json!({ "properties": { "Name": {} } }));
+        assert!(matches!(spec, Ok(ValidationSpec::JsonSchema { .. })));
+    }
+
+    #[test]
+    fn test_validate_records_json_schema() -> Result<()> {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "Name": { "type": "string", "minLength": 1 },
+                "Kit Number": { "type": "integer", "maximum": 99 },
+            },
+        });
+        let report = validate(&ValidationSpec::try_new_json_schema(&schema)?)?;
+        assert_eq!(report.invalid_rows, 2);
+        let columns: Vec<_> = report
+            .failures
+            .iter()
+            .map(|f| (f.line, f.column.as_str()))
+            .collect();
+        assert_eq!(columns, [(3, "Kit Number"), (4, "Name")]);
+        Ok(())
+    }
+}
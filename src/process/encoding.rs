@@ -0,0 +1,160 @@
+use anyhow::Result;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use std::io::{self, Cursor, Read, Write};
+
+// 自动检测编码时采样的字节数
+const SNIFF_SIZE: usize = 64 * 1024;
+
+// 将输入转码为 UTF-8; encoding 为 None 时自动检测, BOM 优先且总是被去除
+pub fn decode_reader<'a>(
+    mut reader: impl Read + 'a,
+    encoding: Option<&'static Encoding>,
+) -> Result<Box<dyn Read + 'a>> {
+    let mut head = Vec::with_capacity(SNIFF_SIZE);
+    reader
+        .by_ref()
+        .take(SNIFF_SIZE as u64)
+        .read_to_end(&mut head)?;
+    let encoding = encoding.unwrap_or_else(|| detect_encoding(&head));
+
+    let reader = Cursor::new(head).chain(reader);
+    let decoder = DecodeReaderBytesBuilder::new()
+        .encoding(Some(encoding))
+        .bom_override(true)
+        .strip_bom(true)
+        .utf8_passthru(true)
+        .build(reader);
+    Ok(Box::new(decoder))
+}
+
+fn detect_encoding(head: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(head) {
+        return encoding;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => UTF_8,
+        // 采样可能截断在多字节字符中间
+        Err(e) if e.error_len().is_none() => UTF_8,
+        Err(_) => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(head, head.len() < SNIFF_SIZE);
+            detector.guess(None, true)
+        }
+    }
+}
+
+// 将写入的 UTF-8 字节转码为目标编码, UTF-16 输出会带上 BOM
+pub struct EncodeWriter<W: Write> {
+    inner: W,
+    encoding: &'static Encoding,
+    pending: Vec<u8>,
+    bom_written: bool,
+}
+
+impl<W: Write> EncodeWriter<W> {
+    pub fn new(inner: W, encoding: &'static Encoding) -> Self {
+        Self {
+            inner,
+            encoding,
+            pending: Vec::new(),
+            bom_written: false,
+        }
+    }
+
+    fn encode(&mut self, s: &str) -> io::Result<()> {
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            let le = self.encoding == UTF_16LE;
+            let mut buf = Vec::with_capacity(s.len() * 2 + 2);
+            let units = (!self.bom_written)
+                .then_some(0xFEFF)
+                .into_iter()
+                .chain(s.encode_utf16());
+            for unit in units {
+                let bytes = if le {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                };
+                buf.extend_from_slice(&bytes);
+            }
+            self.bom_written = true;
+            return self.inner.write_all(&buf);
+        }
+
+        let (encoded, _, had_errors) = self.encoding.encode(s);
+        if had_errors {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Text cannot be encoded as {}", self.encoding.name()),
+            ));
+        }
+        self.inner.write_all(&encoded)
+    }
+}
+
+impl<W: Write> Write for EncodeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(s) => s.len(),
+            // 不完整的多字节字符留到下次写入
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        let rest = self.pending.split_off(valid);
+        let pending = std::mem::replace(&mut self.pending, rest);
+        // from_utf8 已校验过这部分字节
+        let s = std::str::from_utf8(&pending).expect("valid utf-8");
+        self.encode(s)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::GBK;
+
+    fn decode(bytes: &[u8], encoding: Option<&'static Encoding>) -> Result<String> {
+        let mut ret = String::new();
+        decode_reader(bytes, encoding)?.read_to_string(&mut ret)?;
+        Ok(ret)
+    }
+
+    #[test]
+    fn test_decode_reader() -> Result<()> {
+        let text = "姓名,号码\n布冯,77\n";
+        let (gbk, _, _) = GBK.encode(text);
+        assert_eq!(decode(&gbk, Some(GBK))?, text);
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(decode(&utf16, None)?, text);
+
+        let utf8_bom = [b"\xEF\xBB\xBF".as_slice(), text.as_bytes()].concat();
+        assert_eq!(decode(&utf8_bom, None)?, text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_writer() -> Result<()> {
+        let text = "姓名,号码\n布冯,77\n";
+        let mut writer = EncodeWriter::new(Vec::new(), GBK);
+        // 逐字节写入, 验证多字节字符被截断时的处理
+        for b in text.as_bytes() {
+            writer.write_all(&[*b])?;
+        }
+        assert_eq!(writer.inner, GBK.encode(text).0.as_ref());
+
+        let mut writer = EncodeWriter::new(Vec::new(), UTF_16LE);
+        writer.write_all(text.as_bytes())?;
+        assert_eq!(decode(&writer.inner, None)?, text);
+        Ok(())
+    }
+}
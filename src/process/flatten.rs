@@ -0,0 +1,166 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+// 嵌套对象展开为 a.b, 数组展开为 a[0]
+pub fn flatten(value: Value) -> Map<String, Value> {
+    let mut ret = Map::new();
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                flatten_into(&mut ret, k, v);
+            }
+        }
+        v => {
+            ret.insert("value".into(), v);
+        }
+    }
+    ret
+}
+
+fn flatten_into(ret: &mut Map<String, Value>, prefix: String, value: Value) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                flatten_into(ret, format!("{}.{}", prefix, k), v);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, v) in items.into_iter().enumerate() {
+                flatten_into(ret, format!("{}[{}]", prefix, i), v);
+            }
+        }
+        v => {
+            ret.insert(prefix, v);
+        }
+    }
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+// flatten 的逆操作: a.b 还原为嵌套对象, a[0] 还原为数组
+// 下标不会超过字段数, 避免 a[4000000000] 这样的列名申请巨大的数组
+pub fn unflatten(map: Map<String, Value>) -> Result<Value> {
+    let mut ret = Value::Object(Map::new());
+    let max_index = map.len();
+    for (key, value) in map {
+        let path = parse_path(&key, max_index)?;
+        insert_path(&mut ret, &path, value).map_err(|e| e.context(format!("key {:?}", key)))?;
+    }
+    Ok(ret)
+}
+
+fn parse_path(key: &str, max_index: usize) -> Result<Vec<Segment>> {
+    let mut path = Vec::new();
+    for part in key.split('.') {
+        let (name, mut rest) = match part.find('[') {
+            Some(i) => part.split_at(i),
+            None => (part, ""),
+        };
+        path.push(Segment::Key(name.to_string()));
+        while let Some(stripped) = rest.strip_prefix('[') {
+            let Some((index, remain)) = stripped.split_once(']') else {
+                anyhow::bail!("Unclosed index in key {:?}", key);
+            };
+            let index: usize = index.parse()?;
+            if index >= max_index {
+                anyhow::bail!(
+                    "Index {} in key {:?} exceeds the {} columns",
+                    index,
+                    key,
+                    max_index
+                );
+            }
+            path.push(Segment::Index(index));
+            rest = remain;
+        }
+        if !rest.is_empty() {
+            anyhow::bail!("Unexpected {:?} in key {:?}", rest, key);
+        }
+    }
+    Ok(path)
+}
+
+fn insert_path(target: &mut Value, path: &[Segment], value: Value) -> Result<()> {
+    let Some((segment, rest)) = path.split_first() else {
+        if !target.is_null() {
+            anyhow::bail!("Conflicting value");
+        }
+        *target = value;
+        return Ok(());
+    };
+
+    let child = match segment {
+        Segment::Key(k) => {
+            if target.is_null() {
+                *target = Value::Object(Map::new());
+            }
+            let Value::Object(map) = target else {
+                anyhow::bail!("Expect an object at {:?}", k);
+            };
+            map.entry(k.clone()).or_insert(Value::Null)
+        }
+        Segment::Index(i) => {
+            if target.is_null() {
+                *target = Value::Array(Vec::new());
+            }
+            let Value::Array(items) = target else {
+                anyhow::bail!("Expect an array at [{}]", i);
+            };
+            if items.len() <= *i {
+                items.resize(i + 1, Value::Null);
+            }
+            &mut items[*i]
+        }
+    };
+    insert_path(child, rest, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_flatten() {
+        let value = json!({
+            "name": "Buffon",
+            "address": { "city": "Turin", "zip": null },
+            "tags": ["gk", { "since": 2001 }],
+        });
+        let expected = json!({
+            "name": "Buffon",
+            "address.city": "Turin",
+            "address.zip": null,
+            "tags[0]": "gk",
+            "tags[1].since": 2001,
+        });
+        assert_eq!(Value::Object(flatten(value.clone())), expected);
+
+        let Value::Object(flat) = expected else {
+            unreachable!()
+        };
+        assert_eq!(unflatten(flat).unwrap(), value);
+    }
+
+    #[test]
+    fn test_unflatten_conflict() {
+        let flat = json!({ "a": 1, "a.b": 2 });
+        let Value::Object(flat) = flat else {
+            unreachable!()
+        };
+        assert!(unflatten(flat).is_err());
+    }
+
+    #[test]
+    fn test_unflatten_index_out_of_range() {
+        for key in ["a[2]", "a[4000000000]", "a[18446744073709551615]"] {
+            let mut flat = Map::new();
+            flat.insert(key.into(), json!(1));
+            flat.insert("b".into(), json!(2));
+            assert!(unflatten(flat).is_err(), "{}", key);
+        }
+    }
+}
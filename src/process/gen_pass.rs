@@ -1,47 +1,378 @@
 use anyhow::Result;
-use rand::seq::SliceRandom;
-
-const UPPER: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
-const LOWER: &[u8] = b"abcdefghijkmnopqrstuvwxyz";
-const NUMBER: &[u8] = b"123456789";
-const SYMBOL: &[u8] = b"!@#$%^&*_";
-
-pub fn process_genpass(
-    length: u8,
-    uppercase: bool,
-    lowercase: bool,
-    number: bool,
-    symbol: bool,
-) -> Result<String> {
-    let mut rng = rand::thread_rng();
+use serde::Serialize;
+use std::{io::Write, sync::OnceLock};
+
+use super::{csv_output::write_records, rng::SecureRng};
+use crate::{GenPassFormat, OutputFormat};
+
+pub(super) const UPPER: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+pub(super) const LOWER: &[u8] = b"abcdefghijkmnopqrstuvwxyz";
+pub(super) const NUMBER: &[u8] = b"123456789";
+pub(super) const SYMBOL: &[u8] = b"!@#$%^&*_";
+
+// 每行 `<骰子点数>\t<单词>`, 与 EFF 词表格式相同, 共 6^5 = 7776 个单词
+const WORDLIST: &str = include_str!("../../assets/diceware.txt");
+
+// 每类字符为 None 表示禁用, Some(n) 表示至少出现 n 次
+#[derive(Debug, Clone)]
+pub struct PasswordSpec {
+    pub length: u8,
+    pub uppercase: Option<u8>,
+    pub lowercase: Option<u8>,
+    pub number: Option<u8>,
+    pub symbol: Option<u8>,
+    pub symbols: String,
+    pub exclude: String,
+}
+
+// 破解时间按离线慢哈希(每秒 1e4 次)估算
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordReport {
+    pub password: String,
+    pub score: u8,
+    pub entropy: f64,
+    pub crack_time: String,
+}
+
+pub fn process_genpass(spec: &PasswordSpec, rng: &mut SecureRng) -> Result<String> {
+    let classes = spec.classes()?;
     let mut password = Vec::new();
-    let mut chars = Vec::new();
+    for (chars, min) in &classes {
+        for _ in 0..*min {
+            password.push(*rng.choose(chars));
+        }
+    }
 
-    if uppercase {
-        chars.extend_from_slice(UPPER);
-        password.push(*UPPER.choose(&mut rng).expect("char won't be empty"));
+    let chars = all_chars(&classes);
+    while password.len() < spec.length as usize {
+        password.push(*rng.choose(&chars));
     }
-    if lowercase {
-        chars.extend_from_slice(LOWER);
-        password.push(*LOWER.choose(&mut rng).expect("char won't be empty"));
+
+    rng.shuffle(&mut password);
+
+    let pass = String::from_utf8(password)?;
+
+    Ok(pass)
+}
+
+// 单词之间用 separator 连接; digit 为 true 时在随机一个单词后追加一位随机数字
+pub fn process_genphrase(
+    words: u8,
+    separator: &str,
+    capitalize: bool,
+    digit: bool,
+    rng: &mut SecureRng,
+) -> Result<String> {
+    if words == 0 {
+        anyhow::bail!("Number of words must be greater than 0");
     }
-    if number {
-        chars.extend_from_slice(NUMBER);
-        password.push(*NUMBER.choose(&mut rng).expect("char won't be empty"));
+    let wordlist = wordlist();
+    let mut phrase = (0..words)
+        .map(|_| {
+            let word = *rng.choose(wordlist);
+            if capitalize {
+                let (first, rest) = word.split_at(1);
+                first.to_uppercase() + rest
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    if digit {
+        let i = rng.below(phrase.len());
+        phrase[i].push(char::from(b'0' + rng.below(10) as u8));
     }
-    if symbol {
-        chars.extend_from_slice(SYMBOL);
-        password.push(*SYMBOL.choose(&mut rng).expect("char won't be empty"));
+
+    Ok(phrase.join(separator))
+}
+
+// 按字符集大小估算, 忽略每类字符的最少次数约束
+pub fn password_entropy(spec: &PasswordSpec) -> Result<f64> {
+    let chars = all_chars(&spec.classes()?).len();
+    Ok(spec.length as f64 * (chars as f64).log2())
+}
+
+// 首字母大写不增加熵; 追加数字增加 log2(10 * words) 位
+pub fn passphrase_entropy(words: u8, digit: bool) -> f64 {
+    let mut bits = words as f64 * (wordlist().len() as f64).log2();
+    if digit {
+        bits += (10.0 * words as f64).log2();
     }
+    bits
+}
 
-    for _ in 0..(length - password.len() as u8) {
-        let c = chars.choose(&mut rng).expect("char won't be empty");
-        password.push(*c);
+// lines 只输出密码, 其余格式输出完整报告
+pub fn write_passwords<W: Write>(
+    reports: impl Iterator<Item = Result<PasswordReport>>,
+    mut writer: W,
+    format: GenPassFormat,
+) -> Result<()> {
+    let format = match format {
+        GenPassFormat::Lines => {
+            for report in reports {
+                writeln!(writer, "{}", report?.password)?;
+            }
+            return Ok(writer.flush()?);
+        }
+        GenPassFormat::Json => OutputFormat::Json,
+        GenPassFormat::Csv => OutputFormat::Csv,
+    };
+    let records = reports.map(|report| Ok(serde_json::to_value(report?)?));
+    write_records(records, &mut writer, format)?;
+    if matches!(format, OutputFormat::Json) {
+        writeln!(writer)?;
     }
+    writer.flush()?;
 
-    password.shuffle(&mut rng);
+    Ok(())
+}
 
-    let pass = String::from_utf8(password)?;
+impl PasswordReport {
+    pub fn new(password: String, entropy: f64) -> Result<Self> {
+        let estimate = zxcvbn::zxcvbn(&password, &[])?;
+        let crack_time = estimate
+            .crack_times()
+            .offline_slow_hashing_1e4_per_second()
+            .to_string();
+        Ok(Self {
+            score: estimate.score(),
+            entropy: (entropy * 10.0).round() / 10.0,
+            crack_time,
+            password,
+        })
+    }
+}
 
-    Ok(pass)
+impl PasswordSpec {
+    // 返回启用的字符类及其最少次数, 参数不合法时报错而不是在生成时 panic
+    pub(super) fn classes(&self) -> Result<Vec<(Vec<u8>, u8)>> {
+        if self.length == 0 {
+            anyhow::bail!("Password length must be greater than 0");
+        }
+        if let Some(c) = self.symbols.chars().find(|c| !c.is_ascii_punctuation()) {
+            anyhow::bail!("Symbols must be ASCII punctuation, got {:?}", c);
+        }
+
+        let classes = [
+            ("uppercase", UPPER, self.uppercase),
+            ("lowercase", LOWER, self.lowercase),
+            ("number", NUMBER, self.number),
+            ("symbol", self.symbols.as_bytes(), self.symbol),
+        ];
+        let mut enabled = Vec::new();
+        for (name, chars, min) in classes {
+            let Some(min) = min else {
+                continue;
+            };
+            let mut chars = chars
+                .iter()
+                .filter(|c| !self.exclude.as_bytes().contains(c))
+                .copied()
+                .collect::<Vec<_>>();
+            chars.sort_unstable();
+            chars.dedup();
+            if chars.is_empty() {
+                anyhow::bail!("No {} characters left after exclusion", name);
+            }
+            enabled.push((chars, min));
+        }
+
+        if enabled.is_empty() {
+            anyhow::bail!("At least one character class must be enabled");
+        }
+        let required = enabled.iter().map(|(_, min)| *min as usize).sum::<usize>();
+        if required > self.length as usize {
+            anyhow::bail!(
+                "Password length {} is shorter than the {} required characters",
+                self.length,
+                required
+            );
+        }
+        Ok(enabled)
+    }
+}
+
+impl Default for PasswordSpec {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            uppercase: Some(1),
+            lowercase: Some(1),
+            number: Some(1),
+            symbol: Some(1),
+            symbols: String::from_utf8_lossy(SYMBOL).into(),
+            exclude: String::new(),
+        }
+    }
+}
+
+pub(super) fn all_chars(classes: &[(Vec<u8>, u8)]) -> Vec<u8> {
+    classes
+        .iter()
+        .flat_map(|(chars, _)| chars)
+        .copied()
+        .collect()
+}
+
+fn wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&str>> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        WORDLIST
+            .lines()
+            .filter_map(|line| line.split_whitespace().last())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::rng::chi_square;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_wordlist() {
+        let words = wordlist();
+        assert_eq!(words.len(), 7776);
+        assert_eq!(words.iter().collect::<HashSet<_>>().len(), 7776);
+    }
+
+    #[test]
+    fn test_process_genphrase() -> Result<()> {
+        let phrase = process_genphrase(6, "-", false, false, &mut SecureRng::new(None))?;
+        let words = phrase.split('-').collect::<Vec<_>>();
+        assert_eq!(words.len(), 6);
+        assert!(words.iter().all(|w| wordlist().contains(w)));
+
+        let phrase = process_genphrase(4, " ", true, true, &mut SecureRng::new(None))?;
+        assert_eq!(phrase.split(' ').count(), 4);
+        assert!(phrase.split(' ').all(|w| w.starts_with(char::is_uppercase)));
+        assert_eq!(phrase.chars().filter(char::is_ascii_digit).count(), 1);
+
+        assert!(process_genphrase(0, "-", false, false, &mut SecureRng::new(None)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genpass() -> Result<()> {
+        let spec = PasswordSpec {
+            length: 12,
+            uppercase: None,
+            number: Some(4),
+            symbols: "-+".into(),
+            exclude: "abc".into(),
+            ..Default::default()
+        };
+        let pass = process_genpass(&spec, &mut SecureRng::new(None))?;
+        assert_eq!(pass.len(), 12);
+        assert!(!pass.contains(|c: char| c.is_ascii_uppercase() || "abc".contains(c)));
+        assert!(pass.chars().filter(char::is_ascii_digit).count() >= 4);
+        assert!(pass.contains(['-', '+']));
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_distribution() -> Result<()> {
+        let spec = PasswordSpec {
+            uppercase: None,
+            number: None,
+            symbol: None,
+            ..Default::default()
+        };
+        let mut rng = SecureRng::new(Some(2024));
+        let mut counts = vec![0; LOWER.len()];
+        for _ in 0..4000 {
+            for c in process_genpass(&spec, &mut rng)?.bytes() {
+                counts[LOWER.iter().position(|&l| l == c).unwrap()] += 1;
+            }
+        }
+        // 自由度 24 在 p = 0.001 下的临界值
+        assert!(chi_square(&counts) < 51.18);
+
+        let mut a = SecureRng::new(Some(1));
+        let mut b = SecureRng::new(Some(1));
+        assert_eq!(
+            process_genpass(&spec, &mut a)?,
+            process_genpass(&spec, &mut b)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_password_spec_errors() {
+        let invalid = [
+            PasswordSpec {
+                length: 0,
+                ..Default::default()
+            },
+            PasswordSpec {
+                length: 3,
+                ..Default::default()
+            },
+            PasswordSpec {
+                uppercase: None,
+                lowercase: None,
+                number: None,
+                symbol: None,
+                ..Default::default()
+            },
+            PasswordSpec {
+                exclude: "123456789".into(),
+                ..Default::default()
+            },
+            PasswordSpec {
+                symbols: "ab".into(),
+                ..Default::default()
+            },
+        ];
+        for spec in invalid {
+            assert!(
+                process_genpass(&spec, &mut SecureRng::new(None)).is_err(),
+                "{:?}",
+                spec
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_passwords() -> Result<()> {
+        let reports = || {
+            ["correct-horse-battery-staple", "123456"]
+                .into_iter()
+                .map(|p| PasswordReport::new(p.into(), 44.0))
+        };
+        let mut buf = Vec::new();
+        write_passwords(reports(), &mut buf, GenPassFormat::Json)?;
+        let json: serde_json::Value = serde_json::from_slice(&buf)?;
+        assert_eq!(json[1]["password"], "123456");
+        assert_eq!(json[1]["score"], 0);
+        assert_eq!(json[0]["entropy"], 44.0);
+
+        let mut buf = Vec::new();
+        write_passwords(reports(), &mut buf, GenPassFormat::Csv)?;
+        let csv = String::from_utf8(buf)?;
+        assert!(csv.starts_with("password,score,entropy,crack_time\n"));
+        assert_eq!(csv.lines().count(), 3);
+
+        let mut buf = Vec::new();
+        write_passwords(reports(), &mut buf, GenPassFormat::Lines)?;
+        assert_eq!(
+            String::from_utf8(buf)?,
+            "correct-horse-battery-staple\n123456\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_entropy() -> Result<()> {
+        assert!((passphrase_entropy(6, false) - 77.5).abs() < 0.1);
+        let spec = PasswordSpec {
+            uppercase: None,
+            number: None,
+            symbol: None,
+            ..Default::default()
+        };
+        assert!((password_entropy(&spec)? - 16.0 * 25f64.log2()).abs() < 1e-9);
+        Ok(())
+    }
 }
@@ -1,13 +1,60 @@
 mod b64;
+mod csv_cat;
 mod csv_convert;
+mod csv_dedupe;
+mod csv_filter;
+mod csv_group;
+mod csv_infer;
+mod csv_join;
+mod csv_output;
+mod csv_query;
+mod csv_reverse;
+mod csv_sort;
+mod csv_split;
+mod csv_stats;
+mod csv_validate;
+mod encoding;
+mod flatten;
 mod gen_pass;
 mod http_serve;
+mod pass_check;
+mod pass_derive;
+mod pass_pattern;
+mod pass_policy;
+mod rng;
+mod table_parquet;
+mod table_xlsx;
+mod tabular;
 mod text;
 
 pub use b64::{process_decode, process_encode};
-pub use csv_convert::process_csv;
-pub use gen_pass::process_genpass;
+pub use csv_cat::process_csv_cat;
+pub use csv_convert::{process_csv, CsvReaderConfig, CsvTable, CsvTransform};
+pub use csv_dedupe::process_csv_dedupe;
+pub use csv_filter::{CompareOp, RowFilter};
+pub use csv_group::{process_csv_group, Aggregate};
+pub use csv_infer::{ColumnType, Inference};
+pub use csv_join::process_csv_join;
+pub use csv_query::process_csv_query;
+pub use csv_reverse::process_csv_reverse;
+pub use csv_sort::{process_csv_sort, SortKey};
+pub use csv_split::{process_csv_split, SplitBy};
+pub use csv_stats::{process_csv_stats, ColumnStats, ValueCount};
+pub use csv_validate::{process_csv_validate, ValidationFailure, ValidationReport, ValidationSpec};
+pub use encoding::{decode_reader, EncodeWriter};
+pub use gen_pass::{
+    passphrase_entropy, password_entropy, process_genpass, process_genphrase, write_passwords,
+    PasswordReport, PasswordSpec,
+};
 pub use http_serve::process_http_serve;
+pub use pass_check::{process_passcheck, CrackTimeReport, PasswordCheck, PatternReport};
+pub use pass_derive::process_genpass_derive;
+pub use pass_pattern::{pattern_entropy, process_genpattern, pronounceable_pattern};
+pub use pass_policy::{process_genpass_policy, ClassPolicy, LengthRange, PasswordPolicy};
+pub use rng::SecureRng;
+pub use table_parquet::ParquetTable;
+pub use table_xlsx::XlsxTable;
+pub use tabular::{open_table, TableSource, TabularReader};
 pub use text::{
     process_key_generate, process_text_decrypt, process_text_encrypt, process_text_sign,
     process_text_verify,
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{BufRead, BufReader};
+use zxcvbn::{matching::patterns::MatchPattern, time_estimates::CrackTimes};
+
+use crate::read_data;
+
+// 不回显密码本身, 用行号标识
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordCheck {
+    pub line: usize,
+    pub score: u8,
+    pub guesses: u64,
+    pub guesses_log10: f64,
+    pub crack_times: CrackTimeReport,
+    pub patterns: Vec<PatternReport>,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrackTimeReport {
+    pub online_throttled: String,
+    pub online_unthrottled: String,
+    pub offline_slow_hash: String,
+    pub offline_fast_hash: String,
+}
+
+// 只给出匹配片段在密码中的字符位置 [start, end), 不包含片段本身
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternReport {
+    pub pattern: &'static str,
+    pub start: usize,
+    pub end: usize,
+    pub length: usize,
+}
+
+// 每行一个密码, 空行跳过
+pub fn process_passcheck(input: &str) -> Result<Vec<PasswordCheck>> {
+    let reader = BufReader::new(read_data(input)?);
+    let mut checks = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let password = line.strip_suffix('\r').unwrap_or(&line);
+        if password.is_empty() {
+            continue;
+        }
+        checks.push(check_password(i + 1, password)?);
+    }
+    Ok(checks)
+}
+
+fn check_password(line: usize, password: &str) -> Result<PasswordCheck> {
+    let estimate = zxcvbn::zxcvbn(password, &[])?;
+    let patterns = estimate
+        .sequence()
+        .iter()
+        .map(|m| PatternReport {
+            pattern: pattern_name(&m.pattern),
+            start: m.i,
+            end: m.j + 1,
+            length: m.j + 1 - m.i,
+        })
+        .collect();
+    let (warning, suggestions) = match estimate.feedback() {
+        Some(feedback) => (
+            feedback.warning().map(|w| w.to_string()),
+            feedback
+                .suggestions()
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        ),
+        None => (None, Vec::new()),
+    };
+    Ok(PasswordCheck {
+        line,
+        score: estimate.score(),
+        guesses: estimate.guesses(),
+        guesses_log10: (estimate.guesses_log10() * 100.0).round() / 100.0,
+        crack_times: CrackTimeReport::from(estimate.crack_times()),
+        patterns,
+        warning,
+        suggestions,
+    })
+}
+
+fn pattern_name(pattern: &MatchPattern) -> &'static str {
+    match pattern {
+        MatchPattern::Dictionary(p) if p.l33t => "l33t",
+        MatchPattern::Dictionary(p) if p.reversed => "reversed",
+        MatchPattern::Dictionary(_) => "dictionary",
+        MatchPattern::Spatial(_) => "spatial",
+        MatchPattern::Repeat(_) => "repeat",
+        MatchPattern::Sequence(_) => "sequence",
+        MatchPattern::Regex(_) => "regex",
+        MatchPattern::Date(_) => "date",
+        MatchPattern::BruteForce => "bruteforce",
+    }
+}
+
+impl From<CrackTimes> for CrackTimeReport {
+    fn from(times: CrackTimes) -> Self {
+        Self {
+            online_throttled: times.online_throttling_100_per_hour().to_string(),
+            online_unthrottled: times.online_no_throttling_10_per_second().to_string(),
+            offline_slow_hash: times.offline_slow_hashing_1e4_per_second().to_string(),
+            offline_fast_hash: times.offline_fast_hashing_1e10_per_second().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_passcheck() -> Result<()> {
+        let checks = process_passcheck("password123\n\nTr0ub4dour&3\r\nx7#Vq!9zLp@2mK\n")?;
+        assert_eq!(checks.len(), 3);
+        assert_eq!(checks[0].line, 1);
+        assert_eq!(checks[0].score, 0);
+        assert!(checks[0].warning.is_some());
+        assert!(checks[0].patterns.iter().any(|p| p.pattern == "dictionary"));
+        assert_eq!(checks[1].line, 3);
+        assert!(checks[1].patterns.iter().any(|p| p.pattern == "l33t"));
+        let lengths = checks[1].patterns.iter().map(|p| p.length).sum::<usize>();
+        assert_eq!(lengths, "Tr0ub4dour&3".len());
+        assert_eq!(checks[1].patterns[0].start, 0);
+        assert_eq!(checks[2].score, 4);
+        assert!(checks[2].suggestions.is_empty());
+        Ok(())
+    }
+}
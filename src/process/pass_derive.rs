@@ -0,0 +1,145 @@
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use super::{
+    gen_pass::{all_chars, PasswordSpec},
+    rng::below,
+};
+
+// 参数是派生结果的一部分, 修改后所有密码都会变化
+const ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+const ARGON2_ITERATIONS: u32 = 3;
+const STREAM_LEN: usize = 255 * 32;
+
+// Argon2id(主密码, salt = blake3(站点, 登录名)) 得到站点密钥, 再用 HKDF 按 counter 展开成随机字节流,
+// 按 spec 的字符类映射为密码; 同样的输入总是得到同样的密码
+pub fn process_genpass_derive(
+    master: &str,
+    site: &str,
+    login: &str,
+    counter: u32,
+    spec: &PasswordSpec,
+) -> Result<String> {
+    let params = Params::new(ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, 1, Some(32))
+        .map_err(|e| anyhow::anyhow!("Invalid argon2 params: {}", e))?;
+    derive_password(master, site, login, counter, spec, params)
+}
+
+fn derive_password(
+    master: &str,
+    site: &str,
+    login: &str,
+    counter: u32,
+    spec: &PasswordSpec,
+    params: Params,
+) -> Result<String> {
+    if master.is_empty() {
+        anyhow::bail!("Master passphrase must not be empty");
+    }
+    let site = site.trim().to_lowercase();
+    if site.is_empty() {
+        anyhow::bail!("Site must not be empty");
+    }
+    let classes = spec.classes()?;
+
+    let salt = blake3::hash(format!("rcli genpass derive\0{}\0{}", site, login).as_bytes());
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(master.as_bytes(), salt.as_bytes(), &mut key)
+        .map_err(|e| anyhow::anyhow!("Failed to derive key: {}", e))?;
+    let mut stream = vec![0u8; STREAM_LEN];
+    Hkdf::<Sha256>::new(None, &key)
+        .expand(&counter.to_be_bytes(), &mut stream)
+        .map_err(|e| anyhow::anyhow!("Failed to expand key: {}", e))?;
+    let mut stream = Stream::new(&stream);
+
+    let mut password = Vec::new();
+    for (chars, min) in &classes {
+        for _ in 0..*min {
+            password.push(chars[stream.below(chars.len())?]);
+        }
+    }
+    let chars = all_chars(&classes);
+    while password.len() < spec.length as usize {
+        password.push(chars[stream.below(chars.len())?]);
+    }
+    // Fisher-Yates 洗牌, 同样使用派生的字节流
+    for i in (1..password.len()).rev() {
+        password.swap(i, stream.below(i + 1)?);
+    }
+
+    Ok(String::from_utf8(password)?)
+}
+
+// 拒绝采样, 保证各字符等概率
+struct Stream<'a> {
+    bytes: std::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> Stream<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes: bytes.chunks_exact(4),
+        }
+    }
+
+    fn below(&mut self, n: usize) -> Result<usize> {
+        let bytes = &mut self.bytes;
+        let next_u32 = || {
+            bytes
+                .next()
+                .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        match below(next_u32, n) {
+            Some(i) => Ok(i),
+            None => anyhow::bail!("Derived key stream exhausted"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn derive(master: &str, site: &str, counter: u32, spec: &PasswordSpec) -> Result<String> {
+        let params = Params::new(1024, 1, 1, Some(32)).unwrap();
+        derive_password(master, site, "alice", counter, spec, params)
+    }
+
+    #[test]
+    fn test_derive_password() -> Result<()> {
+        let spec = PasswordSpec::default();
+        let pass = derive("correct horse", "example.com", 1, &spec)?;
+        // 固定向量, 派生方案变化时需要同步修改
+        assert_eq!(pass, "@i7%65oDYj%y*pwg");
+        assert_eq!(pass, derive("correct horse", " Example.COM", 1, &spec)?);
+        assert_ne!(pass, derive("correct horse", "example.com", 2, &spec)?);
+        assert_ne!(pass, derive("correct horse", "example.org", 1, &spec)?);
+        assert_ne!(pass, derive("correct horsE", "example.com", 1, &spec)?);
+
+        let spec = PasswordSpec {
+            length: 24,
+            symbol: None,
+            number: Some(6),
+            ..Default::default()
+        };
+        let pass = derive("correct horse", "example.com", 1, &spec)?;
+        assert_eq!(pass.len(), 24);
+        assert!(pass.chars().all(char::is_alphanumeric));
+        assert!(pass.chars().filter(char::is_ascii_digit).count() >= 6);
+
+        assert!(derive("", "example.com", 1, &spec).is_err());
+        Ok(())
+    }
+
+    // 使用真实的 Argon2 参数, 固定线上用户的派生结果; 见 Cargo.toml 中 argon2 的 opt-level
+    #[test]
+    fn test_process_genpass_derive_golden() -> Result<()> {
+        let spec = PasswordSpec::default();
+        let pass = process_genpass_derive("correct horse", "example.com", "alice", 1, &spec)?;
+        assert_eq!(pass, "#8tpFkBj##jRLV4r");
+        Ok(())
+    }
+}
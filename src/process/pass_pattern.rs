@@ -0,0 +1,112 @@
+use anyhow::Result;
+
+use super::{
+    gen_pass::{LOWER, NUMBER, SYMBOL, UPPER},
+    rng::SecureRng,
+};
+
+// 与 gen_pass 一致, 去掉了容易混淆的 I O l 0
+const UPPER_CONSONANT: &[u8] = b"BCDFGHJKLMNPQRSTVWXYZ";
+const LOWER_CONSONANT: &[u8] = b"bcdfghjkmnpqrstvwxyz";
+const UPPER_VOWEL: &[u8] = b"AEU";
+const LOWER_VOWEL: &[u8] = b"aeiou";
+
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    Literal(char),
+    Class(&'static [u8]),
+}
+
+// C/c 辅音, V/v 元音, A/a 字母, 9 数字, ! 符号, `\` 转义, 其余字符原样输出
+pub fn process_genpattern(pattern: &str, rng: &mut SecureRng) -> Result<String> {
+    let password = parse_pattern(pattern)?
+        .into_iter()
+        .map(|token| match token {
+            Token::Literal(c) => c,
+            Token::Class(chars) => *rng.choose(chars) as char,
+        })
+        .collect();
+    Ok(password)
+}
+
+// 字面字符不增加熵
+pub fn pattern_entropy(pattern: &str) -> Result<f64> {
+    let bits = parse_pattern(pattern)?
+        .iter()
+        .map(|token| match token {
+            Token::Literal(_) => 0.0,
+            Token::Class(chars) => (chars.len() as f64).log2(),
+        })
+        .sum();
+    Ok(bits)
+}
+
+// 辅音元音交替, 如 cvcvcv, 便于朗读
+pub fn pronounceable_pattern(length: u8) -> String {
+    (0..length)
+        .map(|i| if i % 2 == 0 { 'c' } else { 'v' })
+        .collect()
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            'C' => Token::Class(UPPER_CONSONANT),
+            'c' => Token::Class(LOWER_CONSONANT),
+            'V' => Token::Class(UPPER_VOWEL),
+            'v' => Token::Class(LOWER_VOWEL),
+            'A' => Token::Class(UPPER),
+            'a' => Token::Class(LOWER),
+            '9' => Token::Class(NUMBER),
+            '!' => Token::Class(SYMBOL),
+            '\\' => match chars.next() {
+                Some(c) => Token::Literal(c),
+                None => anyhow::bail!("Pattern must not end with an escape character"),
+            },
+            c => Token::Literal(c),
+        };
+        tokens.push(token);
+    }
+    if !tokens.iter().any(|t| matches!(t, Token::Class(_))) {
+        anyhow::bail!("Pattern {:?} has no placeholders", pattern);
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_genpattern() -> Result<()> {
+        let pass = process_genpattern("Cvccvc-999-!!\\9", &mut SecureRng::new(None))?;
+        let chars = pass.chars().collect::<Vec<_>>();
+        assert_eq!(chars.len(), 14);
+        assert!(UPPER_CONSONANT.contains(&(chars[0] as u8)));
+        assert!(LOWER_VOWEL.contains(&(chars[1] as u8)));
+        assert_eq!(chars[6], '-');
+        assert!(chars[7..10].iter().all(char::is_ascii_digit));
+        assert!(SYMBOL.contains(&(chars[11] as u8)));
+        assert_eq!(chars[13], '9');
+
+        assert!(process_genpattern("abc\\", &mut SecureRng::new(None)).is_err());
+        assert!(process_genpattern("---", &mut SecureRng::new(None)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pronounceable() -> Result<()> {
+        let pattern = pronounceable_pattern(7);
+        assert_eq!(pattern, "cvcvcvc");
+        let pass = process_genpattern(&pattern, &mut SecureRng::new(None))?;
+        assert!(pass
+            .bytes()
+            .enumerate()
+            .all(|(i, c)| (i % 2 == 1) == LOWER_VOWEL.contains(&c)));
+        let expected = 4.0 * 20f64.log2() + 3.0 * 5f64.log2();
+        assert!((pattern_entropy(&pattern)? - expected).abs() < 1e-9);
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+use super::{
+    gen_pass::{process_genpass, PasswordSpec},
+    rng::SecureRng,
+};
+
+// 例:
+// length: { min: 16, max: 24 }
+// classes: { uppercase: 2, lowercase: 2, number: 1 }
+// forbidden: ["(?i)pass", "123"]
+// max_repeat: 2
+// min_score: 3
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PasswordPolicy {
+    pub length: LengthRange,
+    // 未列出的字符类不会出现; 省略 classes 时四类均启用且至少出现一次
+    #[serde(default = "ClassPolicy::all")]
+    pub classes: ClassPolicy,
+    pub symbols: Option<String>,
+    #[serde(default)]
+    pub exclude: String,
+    #[serde(default)]
+    pub forbidden: Vec<String>,
+    pub max_repeat: Option<usize>,
+    pub min_score: Option<u8>,
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: usize,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum LengthRange {
+    Fixed(u8),
+    Range { min: u8, max: u8 },
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClassPolicy {
+    pub uppercase: Option<u8>,
+    pub lowercase: Option<u8>,
+    pub number: Option<u8>,
+    pub symbol: Option<u8>,
+}
+
+// 不满足策略时重新生成, 超过 max_attempts 次后报告各规则失败的次数
+pub fn process_genpass_policy(policy: &PasswordPolicy, rng: &mut SecureRng) -> Result<String> {
+    let forbidden = policy.forbidden()?;
+    let (min, max) = policy.lengths()?;
+    let mut failures = BTreeMap::new();
+    for _ in 0..policy.max_attempts {
+        let spec = policy.spec(min + rng.below((max - min) as usize + 1) as u8);
+        let password = process_genpass(&spec, rng)?;
+        match policy.check(&password, &forbidden)? {
+            None => return Ok(password),
+            Some(rule) => *failures.entry(rule).or_insert(0) += 1,
+        }
+    }
+
+    let reasons = failures
+        .iter()
+        .map(|(rule, count)| format!("{} ({} times)", rule, count))
+        .collect::<Vec<_>>();
+    anyhow::bail!(
+        "Could not satisfy password policy after {} attempts, failed rules: {}",
+        policy.max_attempts,
+        reasons.join(", ")
+    )
+}
+
+impl PasswordPolicy {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let policy: Self = serde_yaml::from_str(&content)?;
+        policy.lengths()?;
+        policy.forbidden()?;
+        if policy.min_score.is_some_and(|score| score > 4) {
+            anyhow::bail!("min_score must be between 0 and 4");
+        }
+        if policy.max_repeat == Some(0) {
+            anyhow::bail!("max_repeat must be greater than 0");
+        }
+        Ok(policy)
+    }
+
+    pub fn spec(&self, length: u8) -> PasswordSpec {
+        let default = PasswordSpec::default();
+        PasswordSpec {
+            length,
+            uppercase: self.classes.uppercase,
+            lowercase: self.classes.lowercase,
+            number: self.classes.number,
+            symbol: self.classes.symbol,
+            symbols: self.symbols.clone().unwrap_or(default.symbols),
+            exclude: self.exclude.clone(),
+        }
+    }
+
+    // 可生成的长度范围: 下限不小于各字符类最少出现次数之和, 上限也放不下时策略无效
+    fn lengths(&self) -> Result<(u8, u8)> {
+        let (min, max) = self.length.bounds()?;
+        self.spec(max).classes()?;
+        Ok((min.max(self.classes.required()), max))
+    }
+
+    fn forbidden(&self) -> Result<Vec<Regex>> {
+        self.forbidden
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|e| anyhow::anyhow!("Invalid forbidden pattern {:?}: {}", pattern, e))
+            })
+            .collect()
+    }
+
+    // 返回第一条未满足的规则
+    fn check(&self, password: &str, forbidden: &[Regex]) -> Result<Option<String>> {
+        if let Some(re) = forbidden.iter().find(|re| re.is_match(password)) {
+            return Ok(Some(format!("forbidden pattern {:?}", re.as_str())));
+        }
+        if let Some(max) = self.max_repeat {
+            if longest_repeat(password) > max {
+                return Ok(Some(format!("max_repeat {}", max)));
+            }
+        }
+        if let Some(min) = self.min_score {
+            if zxcvbn::zxcvbn(password, &[])?.score() < min {
+                return Ok(Some(format!("min_score {}", min)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl LengthRange {
+    fn bounds(&self) -> Result<(u8, u8)> {
+        let (min, max) = match *self {
+            LengthRange::Fixed(length) => (length, length),
+            LengthRange::Range { min, max } => (min, max),
+        };
+        if min == 0 || min > max {
+            anyhow::bail!("Invalid password length range {}..={}", min, max);
+        }
+        Ok((min, max))
+    }
+}
+
+impl ClassPolicy {
+    fn all() -> Self {
+        Self {
+            uppercase: Some(1),
+            lowercase: Some(1),
+            number: Some(1),
+            symbol: Some(1),
+        }
+    }
+
+    // 超过 u8 时 lengths 中的 classes() 已经报错
+    fn required(&self) -> u8 {
+        [self.uppercase, self.lowercase, self.number, self.symbol]
+            .into_iter()
+            .flatten()
+            .fold(0, u8::saturating_add)
+    }
+}
+
+// 连续相同字符的最大长度
+fn longest_repeat(password: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut last = None;
+    for c in password.chars() {
+        current = if last == Some(c) { current + 1 } else { 1 };
+        longest = longest.max(current);
+        last = Some(c);
+    }
+    longest
+}
+
+fn default_max_attempts() -> usize {
+    1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(yaml: &str) -> Result<PasswordPolicy> {
+        let file = tempfile::NamedTempFile::new()?;
+        fs::write(file.path(), yaml)?;
+        PasswordPolicy::load(file.path())
+    }
+
+    #[test]
+    fn test_process_genpass_policy() -> Result<()> {
+        let policy = policy(
+            "length: { min: 12, max: 14 }\n\
+             classes: { lowercase: 2, number: 2 }\n\
+             forbidden: ['[aeiou]{2}']\n\
+             max_repeat: 1\n\
+             min_score: 3\n",
+        )?;
+        for _ in 0..10 {
+            let pass = process_genpass_policy(&policy, &mut SecureRng::new(None))?;
+            assert!((12..=14).contains(&pass.len()));
+            assert!(pass
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+            assert!(longest_repeat(&pass) <= 1);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_failure_reason() -> Result<()> {
+        let policy = policy("length: 4\nclasses: { number: 1 }\nmin_score: 4\nmax_attempts: 5\n")?;
+        let err = process_genpass_policy(&policy, &mut SecureRng::new(None))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("min_score 4 (5 times)"), "{}", err);
+
+        assert!(self::policy("length: { min: 8, max: 4 }\n").is_err());
+        assert!(self::policy("length: 8\nforbidden: ['(']\n").is_err());
+        assert!(self::policy("length: 8\nmax_lenght: 4\n").is_err());
+        assert!(self::policy("length: { min: 4, max: 7 }\nclasses: { number: 8 }\n").is_err());
+        assert!(self::policy("length: 8\nclasses: {}\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_length_fits_classes() -> Result<()> {
+        let policy = policy(
+            "length: { min: 4, max: 20 }\n\
+             classes: { uppercase: 3, lowercase: 3, number: 2 }\n",
+        )?;
+        let mut rng = SecureRng::new(Some(1));
+        for _ in 0..200 {
+            let pass = process_genpass_policy(&policy, &mut rng)?;
+            assert!((8..=20).contains(&pass.len()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_longest_repeat() {
+        assert_eq!(longest_repeat("aabbbc"), 3);
+        assert_eq!(longest_repeat(""), 0);
+    }
+}
@@ -0,0 +1,144 @@
+use rand::{rngs::OsRng, CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+// 默认直接使用操作系统随机源; 带 seed 的 ChaCha20 只用于生成可复现的测试数据
+pub enum SecureRng {
+    Os(OsRng),
+    Seeded(Box<ChaCha20Rng>),
+}
+
+impl SecureRng {
+    pub fn new(seed: Option<u64>) -> Self {
+        match seed {
+            Some(seed) => SecureRng::Seeded(Box::new(ChaCha20Rng::seed_from_u64(seed))),
+            None => SecureRng::Os(OsRng),
+        }
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        match below(|| Some(self.next_u32()), n) {
+            Some(i) => i,
+            None => unreachable!("OsRng and ChaCha20Rng never run out"),
+        }
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    // Fisher-Yates
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+// 拒绝采样: 只接受落在 n 的整数倍范围内的值, 避免取模偏差; next_u32 返回 None 表示随机源耗尽
+pub(crate) fn below(mut next_u32: impl FnMut() -> Option<u32>, n: usize) -> Option<usize> {
+    assert!(
+        n > 0 && n <= u32::MAX as usize,
+        "range must be in 1..=u32::MAX"
+    );
+    let n = n as u32;
+    let zone = u32::MAX - u32::MAX % n;
+    loop {
+        let x = next_u32()?;
+        if x < zone {
+            return Some((x % n) as usize);
+        }
+    }
+}
+
+impl RngCore for SecureRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            SecureRng::Os(rng) => rng.next_u32(),
+            SecureRng::Seeded(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            SecureRng::Os(rng) => rng.next_u64(),
+            SecureRng::Seeded(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            SecureRng::Os(rng) => rng.fill_bytes(dest),
+            SecureRng::Seeded(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            SecureRng::Os(rng) => rng.try_fill_bytes(dest),
+            SecureRng::Seeded(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+impl CryptoRng for SecureRng {}
+
+// 卡方统计量, 期望各类别等概率
+#[cfg(test)]
+pub(crate) fn chi_square(counts: &[usize]) -> f64 {
+    let total = counts.iter().sum::<usize>() as f64;
+    let expected = total / counts.len() as f64;
+    counts
+        .iter()
+        .map(|&c| (c as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_below_is_uniform() {
+        let mut rng = SecureRng::new(Some(42));
+        // 自由度 6 和 61 在 p = 0.001 下的临界值
+        for (n, critical) in [(7, 22.46), (62, 99.61)] {
+            let mut counts = vec![0; n];
+            for _ in 0..100_000 {
+                counts[rng.below(n)] += 1;
+            }
+            let chi = chi_square(&counts);
+            assert!(chi < critical, "n = {}, chi-square = {}", n, chi);
+        }
+    }
+
+    #[test]
+    fn test_below_rejects_biased_values() {
+        // n = 3 时 u32::MAX 落在拒绝区, 应跳过并取下一个值
+        let mut values = [u32::MAX, 5].into_iter();
+        assert_eq!(below(|| values.next(), 3), Some(2));
+        let mut values = [u32::MAX].into_iter();
+        assert_eq!(below(|| values.next(), 3), None);
+    }
+
+    #[test]
+    fn test_shuffle_is_uniform() {
+        let mut rng = SecureRng::new(Some(7));
+        let mut counts = vec![0; 5];
+        for _ in 0..50_000 {
+            let mut items = [1, 0, 0, 0, 0];
+            rng.shuffle(&mut items);
+            counts[items.iter().position(|&x| x == 1).unwrap()] += 1;
+        }
+        assert!(chi_square(&counts) < 18.47);
+    }
+
+    #[test]
+    fn test_seeded_is_reproducible() {
+        let sample = |seed| {
+            let mut rng = SecureRng::new(Some(seed));
+            (0..8).map(|_| rng.below(1000)).collect::<Vec<_>>()
+        };
+        assert_eq!(sample(1), sample(1));
+        assert_ne!(sample(1), sample(2));
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use parquet::{
+    file::reader::{FileReader, SerializedFileReader},
+    record::reader::RowIter,
+};
+use serde_json::{Map, Value};
+use std::fs::File;
+
+use super::tabular::TabularReader;
+
+// 按 row group 流式读取, 嵌套列输出为 JSON 对象或数组
+pub struct ParquetTable {
+    headers: Vec<String>,
+    rows: RowIter<'static>,
+}
+
+impl ParquetTable {
+    pub fn open(path: &str) -> Result<Self> {
+        let reader = SerializedFileReader::new(File::open(path)?)?;
+        let headers = reader
+            .metadata()
+            .file_metadata()
+            .schema()
+            .get_fields()
+            .iter()
+            .map(|field| field.name().to_string())
+            .collect();
+        Ok(Self {
+            headers,
+            rows: reader.into_iter(),
+        })
+    }
+}
+
+impl Iterator for ParquetTable {
+    type Item = Result<Map<String, Value>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = match self.rows.next()? {
+            Ok(row) => row,
+            Err(e) => return Some(Err(e.into())),
+        };
+        match row.to_json_value() {
+            Value::Object(map) => Some(Ok(map)),
+            _ => Some(Err(anyhow::anyhow!("Parquet row is not a group"))),
+        }
+    }
+}
+
+impl TabularReader for ParquetTable {
+    fn headers(&self) -> &[String] {
+        &self.headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parquet_table() -> Result<()> {
+        let table = ParquetTable::open("assets/juventus.parquet")?;
+        assert_eq!(
+            table.headers(),
+            ["Name", "Position", "DOB", "Nationality", "Kit_Number"]
+        );
+        let rows = table.collect::<Result<Vec<_>>>()?;
+        assert_eq!(rows.len(), 27);
+        assert_eq!(rows[0]["Name"], "Wojciech Szczesny");
+        assert_eq!(rows[0]["Kit_Number"], 1);
+        Ok(())
+    }
+}
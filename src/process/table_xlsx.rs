@@ -0,0 +1,130 @@
+use anyhow::Result;
+use calamine::{open_workbook_auto, Data, Range, Reader};
+use chrono::{NaiveTime, Timelike};
+use serde_json::{Map, Value};
+use std::vec;
+
+use super::{csv_convert::column_name, csv_group::number, tabular::TabularReader};
+
+// 工作表整体读入内存后逐行产出
+pub struct XlsxTable {
+    headers: Vec<String>,
+    rows: vec::IntoIter<Vec<Data>>,
+}
+
+impl XlsxTable {
+    // 未指定 sheet 时读取第一个工作表
+    pub fn open(path: &str, sheet: Option<&str>, has_headers: bool) -> Result<Self> {
+        let mut workbook = open_workbook_auto(path)?;
+        let range = match sheet {
+            Some(name) => {
+                let names = workbook.sheet_names();
+                if !names.iter().any(|n| n == name) {
+                    anyhow::bail!(
+                        "Sheet {:?} not found, available sheets: {}",
+                        name,
+                        names.join(", ")
+                    );
+                }
+                workbook.worksheet_range(name)?
+            }
+            None => match workbook.worksheet_range_at(0) {
+                Some(range) => range?,
+                None => anyhow::bail!("Workbook {} has no sheets", path),
+            },
+        };
+        Ok(Self::from_range(range, has_headers))
+    }
+
+    fn from_range(range: Range<Data>, has_headers: bool) -> Self {
+        let mut rows = range.rows().map(<[Data]>::to_vec).collect::<Vec<_>>();
+        let width = range.width();
+        let headers = if has_headers && !rows.is_empty() {
+            rows.remove(0)
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| match cell {
+                    Data::Empty => column_name(i),
+                    cell => cell.to_string(),
+                })
+                .collect()
+        } else {
+            (0..width).map(column_name).collect()
+        };
+        Self {
+            headers,
+            rows: rows.into_iter(),
+        }
+    }
+}
+
+impl Iterator for XlsxTable {
+    type Item = Result<Map<String, Value>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.next()?;
+        let map = self
+            .headers
+            .iter()
+            .cloned()
+            .zip(row.iter().map(cell_to_value))
+            .collect();
+        Some(Ok(map))
+    }
+}
+
+impl TabularReader for XlsxTable {
+    fn headers(&self) -> &[String] {
+        &self.headers
+    }
+}
+
+// 日期只有日期部分时输出 YYYY-MM-DD, 否则输出 ISO 8601 日期时间
+fn cell_to_value(cell: &Data) -> Value {
+    match cell {
+        Data::Empty => Value::Null,
+        Data::Int(n) => Value::from(*n),
+        Data::Float(n) => number(*n),
+        Data::Bool(b) => Value::Bool(*b),
+        Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => Value::String(s.clone()),
+        Data::DateTime(dt) => match dt.as_datetime() {
+            Some(dt) if dt.time() == NaiveTime::MIN => dt.format("%Y-%m-%d").to_string().into(),
+            Some(dt) if dt.nanosecond() == 0 => dt.format("%Y-%m-%dT%H:%M:%S").to_string().into(),
+            Some(dt) => dt.format("%Y-%m-%dT%H:%M:%S%.3f").to_string().into(),
+            None => number(dt.as_f64()),
+        },
+        Data::Error(e) => Value::String(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xlsx_table() -> Result<()> {
+        let table = XlsxTable::open("assets/juventus.xlsx", None, true)?;
+        assert_eq!(
+            table.headers(),
+            ["Name", "Position", "DOB", "Nationality", "Kit Number"]
+        );
+        let rows = table.collect::<Result<Vec<_>>>()?;
+        assert_eq!(rows.len(), 27);
+        assert_eq!(rows[0]["Name"], "Wojciech Szczesny");
+        assert_eq!(rows[0]["Kit Number"], 1);
+
+        let rows = XlsxTable::open("assets/juventus.xlsx", Some("Club"), true)?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(rows[0]["Founded"], 1897);
+        assert!(XlsxTable::open("assets/juventus.xlsx", Some("Staff"), true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xlsx_without_headers() -> Result<()> {
+        let table = XlsxTable::open("assets/juventus.xlsx", Some("Club"), false)?;
+        assert_eq!(table.headers(), ["col_0", "col_1"]);
+        assert_eq!(table.count(), 2);
+        Ok(())
+    }
+}
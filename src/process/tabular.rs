@@ -0,0 +1,44 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+use super::{csv_convert::CsvTable, table_parquet::ParquetTable, table_xlsx::XlsxTable};
+use crate::{read_data, CsvReaderConfig, Inference};
+
+// 表格数据源: 逐行产出 JSON 对象, 列顺序与 headers 一致
+pub trait TabularReader: Iterator<Item = Result<Map<String, Value>>> {
+    fn headers(&self) -> &[String];
+}
+
+#[derive(Debug, Clone)]
+pub enum TableSource {
+    Csv(CsvReaderConfig),
+    Xlsx {
+        sheet: Option<String>,
+        has_headers: bool,
+    },
+    Parquet,
+}
+
+// xlsx 和 parquet 需要随机读取, 不支持从 stdin 读入; 二者自带类型, 不支持类型推断
+pub fn open_table<'a>(
+    input: &str,
+    source: &TableSource,
+    inference: &Inference,
+) -> Result<Box<dyn TabularReader + 'a>> {
+    if !matches!(source, TableSource::Csv(_)) {
+        if input == "-" {
+            anyhow::bail!("Reading xlsx or parquet from stdin is not supported, pass a file path");
+        }
+        if !matches!(inference, Inference::None) {
+            anyhow::bail!("Type inference and schemas only apply to csv input");
+        }
+    }
+    let table: Box<dyn TabularReader> = match source {
+        TableSource::Csv(config) => Box::new(CsvTable::new(read_data(input)?, config, inference)?),
+        TableSource::Xlsx { sheet, has_headers } => {
+            Box::new(XlsxTable::open(input, sheet.as_deref(), *has_headers)?)
+        }
+        TableSource::Parquet => Box::new(ParquetTable::open(input)?),
+    };
+    Ok(table)
+}
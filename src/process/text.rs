@@ -1,6 +1,6 @@
 use std::{fs, io::Read, path::Path};
 
-use crate::{process_genpass, read_data, TextEncryptFormat, TextSignFormat};
+use crate::{read_data, SecureRng, TextEncryptFormat, TextSignFormat};
 use anyhow::Result;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chacha20poly1305::{
@@ -8,6 +8,7 @@ use chacha20poly1305::{
     ChaCha20Poly1305, Key, Nonce,
 };
 use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 
 pub fn process_text_sign(input: &str, key: &str, format: TextSignFormat) -> Result<Vec<u8>> {
@@ -177,8 +178,8 @@ impl KeyLoader for Blake3 {
 
 impl KeyGenerator for Blake3 {
     fn generate() -> Result<Vec<Vec<u8>>> {
-        let key = process_genpass(32, true, true, true, true)?;
-        let key = key.as_bytes().to_vec();
+        let mut key = vec![0u8; 32];
+        SecureRng::new(None).fill_bytes(&mut key);
         Ok(vec![key])
     }
 }
@@ -245,7 +246,7 @@ impl TextEncryptDecrypt for ChaCha20 {
 
         let cipher: ChaCha20Poly1305 = ChaCha20Poly1305::new(&self.key);
         let nonce: Nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng); // 96-bits; unique per message
-        let encrypted = cipher.encrypt(&nonce, &buf.as_bytes() as &[u8]).unwrap();
+        let encrypted = cipher.encrypt(&nonce, buf.as_bytes()).unwrap();
         let data = ChaCha20EncryptedData {
             encrypt_data: encrypted,
             nonce: nonce.to_vec(),
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use std::{
     fs::File,
-    io::{Cursor, Read},
+    io::{BufWriter, Cursor, Read, Write},
     path::Path,
 };
 
@@ -18,3 +18,13 @@ pub fn read_data(input: &str) -> Result<Box<dyn Read + 'static>> {
 
     Ok(reader)
 }
+
+pub fn write_data(output: &str) -> Result<Box<dyn Write + 'static>> {
+    let writer: Box<dyn Write> = if output == "-" {
+        Box::new(BufWriter::new(std::io::stdout()))
+    } else {
+        Box::new(BufWriter::new(File::create(output)?))
+    };
+
+    Ok(writer)
+}